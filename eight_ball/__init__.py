@@ -1 +1 @@
-from eight_ball.eight_ball import _Ball, _Container, _Simulation
+from eight_ball.eight_ball import _Ball, _Container, _Simulation, HistogramResult, PartnerKind, RunHandle
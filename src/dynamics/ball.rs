@@ -1,3 +1,4 @@
+use crate::dynamics::collide::separation_roots;
 use crate::dynamics::maths::{approx_eq_f64, FloatVec};
 use pyo3::prelude::*;
 
@@ -9,11 +10,40 @@ pub struct Ball {
     pub(crate) vel: FloatVec,
     #[pyo3(get, set)]
     pub(crate) r: f64,
+    #[pyo3(get)]
+    pub(crate) frozen: bool,
+    #[pyo3(get, set)]
+    pub(crate) is_tracer: bool,
+    #[pyo3(get, set)]
+    pub(crate) payload: f64,
 }
 
 impl Ball {
     pub fn new(pos: FloatVec, vel: FloatVec, r: f64) -> Ball {
-        Ball { pos, vel, r }
+        let frozen = false;
+        Ball {
+            pos,
+            vel,
+            r,
+            frozen,
+            is_tracer: false,
+            payload: 0.,
+        }
+    }
+
+    pub fn frozen(pos: FloatVec, r: f64) -> Ball {
+        // A `Ball` which never moves and acts as an infinite mass scatterer
+        // for any other `Ball` which collides with it.
+        let vel = FloatVec::origin();
+        let frozen = true;
+        Ball {
+            pos,
+            vel,
+            r,
+            frozen,
+            is_tracer: false,
+            payload: 0.,
+        }
     }
 
     pub fn pos(&self) -> &FloatVec {
@@ -47,6 +77,31 @@ impl Ball {
 
         approx_eq_f64(centres_distance_squared, distance_squared, 1)
     }
+
+    pub fn overlapping(&self, other: &Ball, tolerance: f64) -> bool {
+        // returns true if the Balls penetrate each other by more than
+        // `tolerance`
+        let relative_displacement = self.pos - other.pos;
+        let distance = relative_displacement.magnitude();
+
+        distance < self.r + other.r - tolerance
+    }
+
+    pub fn collision_roots(&self, other: &Ball) -> Option<(f64, f64)> {
+        // Both times, relative to now, at which the `Ball`s are touching: when
+        // they start to overlap and when they would stop, if they passed
+        // through each other. Either may be in the past. `None` if they never
+        // touch.
+        separation_roots(self.pos - other.pos, self.vel - other.vel, self.r + other.r)
+    }
+
+    pub fn approx_eq(&self, other: &Ball, ulp: u64) -> bool {
+        // returns true if the position, velocity and radius of the Balls are
+        // all equal to within `ulp` units in the last place
+        self.pos.approx_eq(&other.pos, ulp)
+            && self.vel.approx_eq(&other.vel, ulp)
+            && approx_eq_f64(self.r, other.r, ulp)
+    }
 }
 
 #[pyclass(subclass)]
@@ -54,10 +109,99 @@ impl Ball {
 pub struct Container {
     #[pyo3(get, set)]
     pub(crate) r: f64,
+    #[pyo3(get, set)]
+    pub(crate) inner_radius: Option<f64>,
+    pub(crate) centre: FloatVec,
 }
 
 impl Container {
     pub fn new(r: f64) -> Container {
-        Container { r }
+        let inner_radius = None;
+        let centre = FloatVec::origin();
+        Container {
+            r,
+            inner_radius,
+            centre,
+        }
+    }
+
+    pub fn centred_at(r: f64, centre: FloatVec) -> Container {
+        // A circular container whose centre is displaced from the origin.
+        Container {
+            centre,
+            ..Container::new(r)
+        }
+    }
+
+    pub fn annular(outer: f64, inner: f64) -> Container {
+        // A container bounded by two concentric circular walls, with the
+        // `Ball`s moving in the ring between them.
+        let inner_radius = Some(inner);
+        Container {
+            r: outer,
+            inner_radius,
+            centre: FloatVec::origin(),
+        }
+    }
+
+    pub fn offset(&self, pos: FloatVec) -> FloatVec {
+        // The displacement of `pos` from the centre of the container.
+        pos - self.centre
+    }
+
+    pub fn inward_normal_at(&self, point: &FloatVec) -> FloatVec {
+        // The unit normal to the wall nearest `point`, pointing into the
+        // region the `Ball`s move in. As the walls are circles about the
+        // centre, `point` may equally be the centre of a `Ball` touching the
+        // wall as the contact point itself.
+        let offset = self.offset(*point);
+        let distance = offset.magnitude();
+        let outward = offset / distance;
+        match self.inner_radius {
+            Some(inner) if distance - inner < self.r - distance => outward,
+            _ => outward * -1.,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        let inner = self.inner_radius.unwrap_or(0.);
+        std::f64::consts::PI * (self.r * self.r - inner * inner)
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        // The total length of wall, including the inner wall of an annulus.
+        let inner = self.inner_radius.unwrap_or(0.);
+        2. * std::f64::consts::PI * (self.r + inner)
+    }
+}
+
+/// A straight wall inside the container, from `a` to `b`, which the `Ball`s
+/// bounce off on either side.
+#[derive(Clone, Copy)]
+pub struct LineSegment {
+    pub(crate) a: FloatVec,
+    pub(crate) b: FloatVec,
+}
+
+impl LineSegment {
+    pub fn new(a: FloatVec, b: FloatVec) -> LineSegment {
+        LineSegment { a, b }
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.b - self.a).magnitude()
+    }
+
+    pub fn direction(&self) -> FloatVec {
+        // The unit vector pointing along the segment from `a` to `b`.
+        (self.b - self.a).normalize()
+    }
+
+    pub fn closest_point(&self, pos: FloatVec) -> FloatVec {
+        // The point on the segment nearest to `pos`, which is one of the ends
+        // if `pos` lies beyond them.
+        let along = self.direction();
+        let s = (pos - self.a).dot(&along).clamp(0., self.length());
+        self.a + along * s
     }
 }
@@ -14,7 +14,7 @@ impl Centre for Ball {
 
 impl Centre for Container {
     fn get_centre(&self) -> FloatVec {
-        FloatVec::origin()
+        self.centre
     }
 }
 
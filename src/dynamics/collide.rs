@@ -1,5 +1,6 @@
-use crate::dynamics::ball::{Ball, Container};
+use crate::dynamics::ball::{Ball, Container, LineSegment};
 use crate::dynamics::centre::normalised_difference;
+use crate::dynamics::maths::FloatVec;
 use crate::dynamics::DynamicsError;
 
 pub trait Collide<T> {
@@ -14,24 +15,18 @@ pub trait Collide<T> {
 
 impl Collide<Ball> for Ball {
     fn time_to_collision(&self, other: &Ball) -> Option<f64> {
+        // `Ball`s that already overlap, e.g. through rounding, would otherwise
+        // be scheduled to collide as they separate. If they are still
+        // approaching, they collide straight away instead, and if they are
+        // separating they are left to do so.
         let dr = self.pos - other.pos;
         let dv = self.vel - other.vel;
-        let dv_squared = dv.dot(&dv);
+        let contact = self.r + other.r;
 
-        let lhs = dv_squared * (self.r + other.r) * (self.r + other.r);
-        let rhs = dr.cross_squared(&dv);
-
-        if lhs < rhs {
-            // equivalent to asking if discriminant < 0
-            None
-        } else {
-            // find the smallest positive solution
-            let disc = lhs - rhs;
-            let r1 = -(dv.dot(&dr) + disc.sqrt()) / dv_squared;
-            let r2 = -(dv.dot(&dr) - disc.sqrt()) / dv_squared;
-
-            smallest_positive(r1, r2)
+        if dr.dot(&dr) < contact * contact {
+            return (dr.dot(&dv) < 0.).then_some(0.);
         }
+        time_to_separation(dr, dv, contact)
     }
 
     fn collide(&mut self, other: &mut Ball) -> Result<(), DynamicsError> {
@@ -40,11 +35,26 @@ impl Collide<Ball> for Ball {
         // tangential to the point of collision on both balls is conserved,
         // as the normal force exerted by each ball is strictly perpendicular
         // to this. In the direction of the balls' normal, the velocities are
-        // swapped.
+        // swapped. If either of the balls is frozen, it is treated as having
+        // infinite mass: the other ball reflects off it as it would off the
+        // container, and the frozen ball keeps its (zero) velocity.
 
         let normed_normal = normalised_difference(self, other)?;
         let loc = normed_normal.anti_clockwise_perpendicular();
 
+        match (self.frozen, other.frozen) {
+            (true, true) => return Ok(()),
+            (false, true) => {
+                self.set_vel(reflect(self.vel, loc, normed_normal));
+                return Ok(());
+            }
+            (true, false) => {
+                other.set_vel(reflect(other.vel, loc, normed_normal));
+                return Ok(());
+            }
+            (false, false) => (),
+        }
+
         let alpha_1 = self.vel.dot(&loc);
         let beta_1 = self.vel.dot(&normed_normal);
 
@@ -59,41 +69,116 @@ impl Collide<Ball> for Ball {
 
 impl Collide<Container> for Ball {
     fn time_to_collision(&self, other: &Container) -> Option<f64> {
-        let dr = self.pos;
+        // If the container is annular, the `Ball` may also collide with the
+        // inner wall, which it approaches from the outside.
+        let dr = other.offset(self.pos);
         let dv = self.vel;
-        let dv_squared = dv.dot(&dv);
-
-        let lhs = dv_squared * (self.r - other.r) * (self.r - other.r);
-        let rhs = dr.cross_squared(&dv);
 
-        if lhs < rhs {
-            // equivalent to asking if discriminant < 0
-            None
-        } else {
-            // find the smallest positive solution
-            let disc = lhs - rhs;
-            let r1 = -(dv.dot(&dr) + disc.sqrt()) / dv_squared;
-            let r2 = -(dv.dot(&dr) - disc.sqrt()) / dv_squared;
+        let outer = time_to_separation(dr, dv, self.r - other.r);
+        let inner = other
+            .inner_radius
+            .and_then(|inner| time_to_separation(dr, dv, self.r + inner));
 
-            smallest_positive(r1, r2)
+        match (outer, inner) {
+            (Some(outer), Some(inner)) => Some(outer.min(inner)),
+            (outer, inner) => outer.or(inner),
         }
     }
 
     fn collide(&mut self, other: &mut Container) -> Result<(), DynamicsError> {
         // Calculate and update the trajectory for a `Ball` colliding with a container, i.e.
-        // a stationary `Ball` which we also assume totally contains `self`.
+        // a stationary `Ball` which we also assume totally contains `self`. The normal to
+        // the inner wall of an annular container is also radial, so the reflection is the
+        // same whichever wall is hit.
 
         let normed_normal = normalised_difference(self, other)?;
         let loc = normed_normal.anti_clockwise_perpendicular();
 
-        let alpha = self.vel.dot(&loc);
-        let beta = self.vel.dot(&normed_normal);
+        self.set_vel(reflect(self.vel, loc, normed_normal));
+        Ok(())
+    }
+}
+
+impl Collide<LineSegment> for Ball {
+    fn time_to_collision(&self, other: &LineSegment) -> Option<f64> {
+        // The `Ball` first touches the segment either along its length, when
+        // its centre comes within `r` of the line through the segment, or at
+        // one of its ends, which act as points.
+        let along = other.direction();
+        let normal = along.anti_clockwise_perpendicular();
+        let distance = (self.pos - other.a).dot(&normal);
+        let approach = self.vel.dot(&normal);
+
+        let side = if distance.abs() > self.r && distance * approach < 0. {
+            let t = (distance.abs() - self.r) / approach.abs();
+            let s = (self.pos + self.vel * t - other.a).dot(&along);
+            (0. ..=other.length()).contains(&s).then_some(t)
+        } else {
+            None
+        };
+        let ends = [other.a, other.b]
+            .into_iter()
+            .filter_map(|end| time_to_separation(self.pos - end, self.vel, self.r));
+        side.into_iter().chain(ends).min_by(f64::total_cmp)
+    }
 
-        self.set_vel(alpha * loc - beta * normed_normal);
+    fn collide(&mut self, other: &mut LineSegment) -> Result<(), DynamicsError> {
+        // The segment is fixed, so the `Ball` reflects off it as it would off
+        // the container, about the normal through the point of contact.
+        let offset = self.pos - other.closest_point(self.pos);
+        if offset.magnitude() == 0. {
+            return Err(DynamicsError::IntersectingParticles);
+        }
+        let normed_normal = offset.normalize();
+        let loc = normed_normal.anti_clockwise_perpendicular();
+
+        self.set_vel(reflect(self.vel, loc, normed_normal));
         Ok(())
     }
 }
 
+fn reflect(vel: FloatVec, loc: FloatVec, normed_normal: FloatVec) -> FloatVec {
+    // Reflect `vel` off a stationary, infinitely massive surface: the component
+    // along the line of centres, `loc`, is kept while the normal component is
+    // reversed.
+    let alpha = vel.dot(&loc);
+    let beta = vel.dot(&normed_normal);
+
+    alpha * loc - beta * normed_normal
+}
+
+fn time_to_separation(dr: FloatVec, dv: FloatVec, separation: f64) -> Option<f64> {
+    // Find the time until two centres, displaced by `dr` and with relative
+    // velocity `dv`, are next `separation` apart.
+    let (r1, r2) = separation_roots(dr, dv, separation)?;
+    smallest_positive(r1, r2)
+}
+
+pub(crate) fn separation_roots(dr: FloatVec, dv: FloatVec, separation: f64) -> Option<(f64, f64)> {
+    // Find both times, in ascending order, at which two centres, displaced by
+    // `dr` and with relative velocity `dv`, are `separation` apart.
+    let dv_squared = dv.dot(&dv);
+    if dv_squared == 0. {
+        // Relatively stationary: the separation never changes, and dividing by
+        // `dv_squared` below would give NaN.
+        return None;
+    }
+
+    let lhs = dv_squared * separation * separation;
+    let rhs = dr.cross_squared(&dv);
+
+    if lhs < rhs {
+        // equivalent to asking if discriminant < 0
+        None
+    } else {
+        let disc = lhs - rhs;
+        let r1 = -(dv.dot(&dr) + disc.sqrt()) / dv_squared;
+        let r2 = -(dv.dot(&dr) - disc.sqrt()) / dv_squared;
+
+        Some((r1, r2))
+    }
+}
+
 fn smallest_positive(a: f64, b: f64) -> Option<f64> {
     let (x_min, x_max) = (a.min(b), a.max(b));
     if x_min.is_sign_positive() {
@@ -118,6 +118,30 @@ impl FloatVec {
         *self / self.magnitude()
     }
 
+    pub fn project_onto(&self, axis: &FloatVec) -> FloatVec {
+        // The component of `self` parallel to `axis`, which is assumed to be
+        // a unit vector.
+        self.dot(axis) * *axis
+    }
+
+    pub fn reject_from(&self, axis: &FloatVec) -> FloatVec {
+        // The component of `self` perpendicular to `axis`, which is assumed
+        // to be a unit vector.
+        *self - self.project_onto(axis)
+    }
+
+    pub fn round_to(&self, decimals: u32) -> FloatVec {
+        // Round both components to `decimals` decimal places, e.g. to cut
+        // down the size of compressed output.
+        let scale = 10f64.powi(decimals as i32);
+        let round = |v: f64| (v * scale).round() / scale;
+        FloatVec::new(round(self.x), round(self.y))
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
     pub fn approx_eq(&self, other: &FloatVec, ulp: u64) -> bool {
         // Determine if the two SafeFloatVecs are equal to within
         // `n` units in the last place. As `n` is by default 1 and
@@ -15,14 +15,37 @@ pub enum DynamicsError {
     PointParticleCollision,
     IntersectingParticles,
     SimulationFailure,
+    TimeLimitExceeded,
+    ForceFieldFailure,
+    NonFiniteState { collision: usize, time: f64 },
+    ReplayMismatch { event: usize },
+    MistimedCollision { collision: usize, gap: f64 },
+    Uninitialised,
 }
 
 #[pymethods]
 impl Ball {
     #[new]
-    #[pyo3(signature = (pos=(0f64, 0f64), vel=(0f64, 0f64), r=0.01f64))]
-    fn py_new(pos: (f64, f64), vel: (f64, f64), r: f64) -> Self {
-        Self::new(pos.into(), vel.into(), r)
+    #[pyo3(signature = (pos=(0f64, 0f64), vel=(0f64, 0f64), r=0.01f64, frozen=false, is_tracer=false, payload=0f64))]
+    fn py_new(
+        pos: (f64, f64),
+        vel: (f64, f64),
+        r: f64,
+        frozen: bool,
+        is_tracer: bool,
+        payload: f64,
+    ) -> PyResult<Self> {
+        let mut ball = if frozen {
+            if vel != (0., 0.) {
+                return Err(PyValueError::new_err("A frozen ball cannot move."));
+            }
+            Self::frozen(pos.into(), r)
+        } else {
+            Self::new(pos.into(), vel.into(), r)
+        };
+        ball.is_tracer = is_tracer;
+        ball.payload = payload;
+        Ok(ball)
     }
 
     #[getter(pos)]
@@ -42,9 +65,22 @@ impl Ball {
     }
 
     #[setter(vel)]
-    fn py_set_vel(&mut self, vel: (f64, f64)) {
+    fn py_set_vel(&mut self, vel: (f64, f64)) -> PyResult<()> {
+        if self.frozen && vel != (0., 0.) {
+            return Err(PyValueError::new_err("A frozen ball cannot move."));
+        }
         let (x, y) = vel;
-        self.vel = FloatVec { x, y }
+        self.vel = FloatVec { x, y };
+        Ok(())
+    }
+
+    #[setter(frozen)]
+    fn py_set_frozen(&mut self, frozen: bool) {
+        // Freezing a `Ball` stops it dead, as a frozen `Ball` never moves.
+        if frozen {
+            self.vel = FloatVec::origin();
+        }
+        self.frozen = frozen;
     }
 
     #[pyo3(signature = (t, delta=1e-6))]
@@ -69,6 +105,17 @@ impl Ball {
         })
     }
 
+    /// Both times at which this ball and `other` touch, when they would start
+    /// and stop overlapping if they could pass through each other, or `None`
+    /// if they never touch. Useful for looking into near misses.
+    #[pyo3(name = "collision_roots")]
+    fn py_collision_roots(&self, other: Py<Ball>) -> Option<(f64, f64)> {
+        Python::with_gil(|py| {
+            let other = other.borrow(py);
+            self.collision_roots(&other)
+        })
+    }
+
     #[pyo3(name = "collide")]
     fn py_collide(&mut self, other: Py<Ball>) -> PyResult<()> {
         Python::with_gil(|py| {
@@ -96,6 +143,14 @@ impl Ball {
         })
     }
 
+    #[pyo3(name = "approx_eq", signature = (other, ulp=1))]
+    fn py_approx_eq(&self, other: Py<Ball>, ulp: u64) -> bool {
+        Python::with_gil(|py| {
+            let other = other.borrow(py);
+            self.approx_eq(&other, ulp)
+        })
+    }
+
     fn v_squared(&self) -> f64 {
         self.vel.dot(&self.vel)
     }
@@ -104,9 +159,42 @@ impl Ball {
 #[pymethods]
 impl Container {
     #[new]
-    #[pyo3(signature = (r=1f64))]
-    fn py_new(r: f64) -> Self {
-        Self::new(r)
+    #[pyo3(signature = (r=1f64, centre=(0f64, 0f64)))]
+    fn py_new(r: f64, centre: (f64, f64)) -> Self {
+        Self::centred_at(r, centre.into())
+    }
+
+    #[getter(centre)]
+    fn py_get_centre(&self) -> (f64, f64) {
+        (self.centre.x, self.centre.y)
+    }
+
+    #[setter(centre)]
+    fn py_set_centre(&mut self, centre: (f64, f64)) {
+        self.centre = centre.into()
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "annular")]
+    fn py_annular(outer: f64, inner: f64) -> PyResult<Self> {
+        if !(0. < inner && inner < outer) {
+            return Err(PyValueError::new_err(
+                "The inner radius must be positive and smaller than the outer radius.",
+            ));
+        }
+        Ok(Self::annular(outer, inner))
+    }
+
+    /// The area available to the balls, excluding the hole of an annulus.
+    #[pyo3(name = "area")]
+    fn py_area(&self) -> f64 {
+        self.area()
+    }
+
+    /// The total length of the walls, including the inner wall of an annulus.
+    #[pyo3(name = "perimeter")]
+    fn py_perimeter(&self) -> f64 {
+        self.perimeter()
     }
 }
 
@@ -123,4 +211,144 @@ mod test {
 
         assert!(maths::approx_eq_f64(ttc, 0.8, 1));
     }
+
+    #[test]
+    fn frozen_collision() {
+        let mut b1 = ball::Ball::new((0., 0.).into(), (1., 0.).into(), 0.1);
+        let mut b2 = ball::Ball::frozen((1., 0.).into(), 0.1);
+        let ttc = b1.time_to_collision(&b2).unwrap();
+        b1.step(ttc);
+        b2.step(ttc);
+        b1.collide(&mut b2).unwrap();
+
+        assert!(b1.vel().approx_eq(&(-1., 0.).into(), 1));
+        assert_eq!(*b2.vel(), FloatVec::origin());
+        assert_eq!(*b2.pos(), (1., 0.).into());
+    }
+
+    #[test]
+    fn frozen_velocity() {
+        pyo3::prepare_freethreaded_python();
+        assert!(Ball::py_new((0., 0.), (1., 0.), 0.1, true, false, 0.).is_err());
+        let mut b = Ball::py_new((0., 0.), (0., 0.), 0.1, true, false, 0.).unwrap();
+        assert!(b.py_set_vel((0., 1.)).is_err());
+
+        let mut b = Ball::py_new((0., 0.), (1., 2.), 0.1, false, false, 0.).unwrap();
+        b.py_set_frozen(true);
+        assert!(b.frozen);
+        assert_eq!(*b.vel(), FloatVec::origin());
+        b.py_set_frozen(false);
+        b.py_set_vel((0., 1.)).unwrap();
+    }
+
+    #[test]
+    fn approx_eq() {
+        let b1 = ball::Ball::new((0.5, 0.).into(), (1., 0.).into(), 0.1);
+        let nudged = f64::from_bits(0.5f64.to_bits() + 1);
+        let b2 = ball::Ball::new((nudged, 0.).into(), (1., 0.).into(), 0.1);
+
+        assert!(b1.approx_eq(&b2, 2));
+        assert!(!b1.approx_eq(&b2, 0));
+    }
+
+    #[test]
+    fn projection() {
+        let v = FloatVec::new(3., 4.);
+        let x_axis = FloatVec::new(1., 0.);
+
+        assert_eq!(v.project_onto(&x_axis), FloatVec::new(3., 0.));
+        assert_eq!(v.reject_from(&x_axis), FloatVec::new(0., 4.));
+    }
+
+    #[test]
+    fn annular_container() {
+        let mut container = ball::Container::annular(1., 0.4);
+        let mut b = ball::Ball::new((0.7, 0.).into(), (-1., 0.).into(), 0.1);
+
+        let ttc = b.time_to_collision(&container).unwrap();
+        assert!(maths::approx_eq_f64(ttc, 0.2, 2));
+        b.step(ttc);
+        b.collide(&mut container).unwrap();
+        assert!(b.vel().approx_eq(&(1., 0.).into(), 1));
+
+        let ttc = b.time_to_collision(&container).unwrap();
+        assert!(maths::approx_eq_f64(ttc, 0.4, 2));
+        b.step(ttc);
+        b.collide(&mut container).unwrap();
+        assert!(b.vel().approx_eq(&(-1., 0.).into(), 1));
+    }
+
+    #[test]
+    fn container_measures() {
+        let pi = std::f64::consts::PI;
+        let circle = ball::Container::new(2.);
+        assert!((circle.area() - 4. * pi).abs() < 1e-12);
+        assert!((circle.perimeter() - 4. * pi).abs() < 1e-12);
+
+        let annulus = ball::Container::annular(2., 1.);
+        assert!((annulus.area() - 3. * pi).abs() < 1e-12);
+        assert!((annulus.perimeter() - 6. * pi).abs() < 1e-12);
+    }
+
+    #[test]
+    fn relatively_stationary() {
+        let b1 = ball::Ball::new((0., 0.).into(), (1., 0.5).into(), 0.1);
+        let b2 = ball::Ball::new((0.5, 0.).into(), (1., 0.5).into(), 0.1);
+        assert_eq!(b1.time_to_collision(&b2), None);
+
+        let still = ball::Ball::new((0., 0.).into(), (0., 0.).into(), 0.1);
+        assert_eq!(still.time_to_collision(&ball::Container::new(1.)), None);
+    }
+
+    #[test]
+    fn offset_container() {
+        let mut container = ball::Container::centred_at(1., (2., 1.).into());
+        let mut b = ball::Ball::new((2.5, 1.).into(), (1., 0.).into(), 0.1);
+
+        let ttc = b.time_to_collision(&container).unwrap();
+        assert!(maths::approx_eq_f64(ttc, 0.4, 2));
+        b.step(ttc);
+        b.collide(&mut container).unwrap();
+        assert!(b.vel().approx_eq(&(-1., 0.).into(), 1));
+
+        let ttc = b.time_to_collision(&container).unwrap();
+        assert!(maths::approx_eq_f64(ttc, 1.8, 2));
+    }
+
+    #[test]
+    fn overlapping_collision() {
+        let b1 = ball::Ball::new((0., 0.).into(), (1., 0.).into(), 0.1);
+        let approaching = ball::Ball::new((0.19, 0.).into(), (-1., 0.).into(), 0.1);
+        let separating = ball::Ball::new((0.19, 0.).into(), (2., 0.).into(), 0.1);
+
+        assert_eq!(b1.time_to_collision(&approaching), Some(0.));
+        assert_eq!(b1.time_to_collision(&separating), None);
+    }
+
+    #[test]
+    fn inward_normal() {
+        let circle = ball::Container::new(1.);
+        let normal = circle.inward_normal_at(&(0.6, 0.8).into());
+        assert!(normal.approx_eq(&(-0.6, -0.8).into(), 1));
+
+        let annulus = ball::Container::annular(1., 0.4);
+        let normal = annulus.inward_normal_at(&(0., -0.5).into());
+        assert!(normal.approx_eq(&(0., -1.).into(), 1));
+    }
+
+    #[test]
+    fn collision_roots() {
+        // The balls overlap while their centres are less than 0.2 apart, from
+        // t = 0.4 to t = 0.6.
+        let b1 = ball::Ball::new((0., 0.).into(), (1., 0.).into(), 0.1);
+        let b2 = ball::Ball::new((1., 0.).into(), (-1., 0.).into(), 0.1);
+        let (entry, exit) = b1.collision_roots(&b2).unwrap();
+
+        assert!((entry - 0.4).abs() < 1e-12);
+        assert!((exit - 0.6).abs() < 1e-12);
+        assert_eq!(b1.time_to_collision(&b2), Some(entry));
+
+        let miss = ball::Ball::new((1., 0.5).into(), (-1., 0.).into(), 0.1);
+        assert_eq!(b1.collision_roots(&miss), None);
+    }
 }
@@ -2,6 +2,9 @@ use pyo3::prelude::*;
 mod dynamics;
 use dynamics::ball::{Ball, Container};
 mod simulation;
+use simulation::event::PartnerKind;
+use simulation::handle::RunHandle;
+use simulation::histogram::HistogramResult;
 use simulation::simulate::Simulation;
 
 #[pymodule]
@@ -9,5 +12,8 @@ fn eight_ball(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<Ball>()?;
     m.add_class::<Container>()?;
     m.add_class::<Simulation>()?;
+    m.add_class::<HistogramResult>()?;
+    m.add_class::<PartnerKind>()?;
+    m.add_class::<RunHandle>()?;
     Ok(())
 }
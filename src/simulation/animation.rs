@@ -0,0 +1,57 @@
+use serde::Serialize;
+
+use super::simulate::SimulationCore;
+
+/// The geometry of the container, as written to an animation bundle.
+#[derive(Serialize)]
+pub struct ContainerGeometry {
+    r: f64,
+    inner_radius: Option<f64>,
+    centre: (f64, f64),
+}
+
+/// The positions of the `Ball`s at time `t`.
+#[derive(Serialize)]
+pub struct Frame {
+    t: f64,
+    positions: Vec<(f64, f64)>,
+}
+
+/// Everything a visualiser needs to animate a run: the fixed geometry of the
+/// container and the `Ball`s, and a sequence of frames.
+#[derive(Serialize)]
+pub struct Animation {
+    container: ContainerGeometry,
+    radii: Vec<f64>,
+    frames: Vec<Frame>,
+}
+
+impl Animation {
+    pub fn new(sim: &SimulationCore) -> Self {
+        // Start an animation of `sim` with no frames.
+        let container = ContainerGeometry {
+            r: sim.container.r,
+            inner_radius: sim.container.inner_radius,
+            centre: (sim.container.centre.x, sim.container.centre.y),
+        };
+        let radii = sim.balls.iter().map(|ball| ball.r).collect();
+        Animation {
+            container,
+            radii,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push_frame(&mut self, sim: &SimulationCore) {
+        // Record the current positions of the `Ball`s of `sim` as a frame.
+        let positions = sim
+            .balls
+            .iter()
+            .map(|ball| (ball.pos().x, ball.pos().y))
+            .collect();
+        self.frames.push(Frame {
+            t: sim.reported_time(sim.global_time),
+            positions,
+        });
+    }
+}
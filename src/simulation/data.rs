@@ -1,9 +1,14 @@
 use crate::dynamics::ball::Ball;
+use crate::dynamics::maths::FloatVec;
 
-use super::{event::CollisionPartner, simulate::Simulation};
+use super::{event::CollisionPartner, simulate::SimulationCore};
+
+/// The velocities of the participants in a collision, as `(vx, vy)`.
+type Velocities = Vec<(f64, f64)>;
 
 /// A chunk of data that represents the state of the collision directly before
 /// it occurs
+#[allow(clippy::enum_variant_names)] // named after the `DataEvent` variants
 pub enum PreData {
     BallCollision {
         time: f64,
@@ -14,11 +19,18 @@ pub enum PreData {
         time: f64,
         index: usize,
         pre: Ball,
+        normal: FloatVec,
+    },
+    BaffleCollision {
+        time: f64,
+        index: usize,
+        baffle: usize,
+        pre: Ball,
     },
 }
 
 impl PreData {
-    pub fn from_indices(sim: &Simulation, i: usize, j: CollisionPartner) -> Self {
+    pub fn from_indices(sim: &SimulationCore, i: usize, j: CollisionPartner) -> Self {
         let time = sim.global_time;
         let ball = sim.balls[i].clone();
         match j {
@@ -34,20 +46,34 @@ impl PreData {
             }
             CollisionPartner::Container => {
                 let index = i;
+                let normal = sim.container.inward_normal_at(ball.pos());
                 let pre = ball;
-                PreData::ContainerCollision { time, index, pre }
+                PreData::ContainerCollision {
+                    time,
+                    index,
+                    pre,
+                    normal,
+                }
             }
+            CollisionPartner::Baffle(baffle) => PreData::BaffleCollision {
+                time,
+                index: i,
+                baffle,
+                pre: ball,
+            },
         }
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum PostData {
     BallCollision { posts: (Ball, Ball) },
     ContainerCollision { post: Ball },
+    BaffleCollision { post: Ball },
 }
 
 impl PostData {
-    pub fn from_indices(sim: &Simulation, i: usize, j: CollisionPartner) -> Self {
+    pub fn from_indices(sim: &SimulationCore, i: usize, j: CollisionPartner) -> Self {
         let ball = sim.balls[i].clone();
         match j {
             CollisionPartner::Ball(j) => {
@@ -56,6 +82,7 @@ impl PostData {
                 PostData::BallCollision { posts }
             }
             CollisionPartner::Container => PostData::ContainerCollision { post: ball },
+            CollisionPartner::Baffle(_) => PostData::BaffleCollision { post: ball },
         }
     }
 }
@@ -69,12 +96,25 @@ pub enum DataEvent {
         pres: (Ball, Ball),
         posts: (Ball, Ball),
     },
+    /// The `normal` is the unit normal to the wall that was hit, pointing into
+    /// the container.
     ContainerCollision {
         time: f64,
         index: usize,
         pre: Ball,
         post: Ball,
+        normal: FloatVec,
+    },
+    /// A `Ball` bounced off the baffle at index `baffle`.
+    BaffleCollision {
+        time: f64,
+        index: usize,
+        baffle: usize,
+        pre: Ball,
+        post: Ball,
     },
+    /// A `Ball` left through the hole in the container, and was removed.
+    Escape { time: f64, index: usize, pre: Ball },
 }
 
 impl From<(PreData, PostData)> for DataEvent {
@@ -94,35 +134,61 @@ impl From<(PreData, PostData)> for DataEvent {
                 posts,
             },
             (
-                PreData::ContainerCollision { time, index, pre },
+                PreData::ContainerCollision {
+                    time,
+                    index,
+                    pre,
+                    normal,
+                },
                 PostData::ContainerCollision { post },
             ) => DataEvent::ContainerCollision {
                 time,
                 index,
                 pre,
                 post,
+                normal,
             },
             (
-                PreData::BallCollision {
-                    time: _,
-                    indices: _,
-                    pres: _,
-                },
-                PostData::ContainerCollision { post: _ },
-            ) => panic!(),
-            (
-                PreData::ContainerCollision {
-                    time: _,
-                    index: _,
-                    pre: _,
+                PreData::BaffleCollision {
+                    time,
+                    index,
+                    baffle,
+                    pre,
                 },
-                PostData::BallCollision { posts: _ },
-            ) => panic!(),
+                PostData::BaffleCollision { post },
+            ) => DataEvent::BaffleCollision {
+                time,
+                index,
+                baffle,
+                pre,
+                post,
+            },
+            // The data before and after must describe the same kind of
+            // collision.
+            _ => panic!(),
         }
     }
 }
 
 impl DataEvent {
+    /// The contribution of the collision to the off-diagonal virial,
+    /// `sum_i x_i * dp_y_i` over the participants, with positions measured from
+    /// `origin`. All balls have unit mass. For a pair of balls only their
+    /// separation matters, so `origin` is only used for the container and
+    /// baffles. Escapes contribute nothing.
+    pub fn shear_virial(&self, origin: &FloatVec) -> f64 {
+        match self {
+            DataEvent::BallCollision { pres, posts, .. } => {
+                (pres.0.pos.x - pres.1.pos.x) * (posts.0.vel.y - pres.0.vel.y)
+            }
+            DataEvent::ContainerCollision { pre, post, .. }
+            | DataEvent::BaffleCollision { pre, post, .. } => {
+                (pre.pos.x - origin.x) * (post.vel.y - pre.vel.y)
+            }
+            DataEvent::Escape { .. } => 0.,
+        }
+    }
+
     /// Calculate the momentum imparted on the container by the collision. If
     /// the collision is between two balls, then this will return the `None`
     /// variant, otherwise this will be equal to the magnitude in the change
@@ -140,10 +206,32 @@ impl DataEvent {
                 index: _,
                 pre,
                 post,
+                normal: _,
             } => {
                 let delta_v = pre.vel - post.vel;
                 Some(delta_v.magnitude())
             }
+            DataEvent::BaffleCollision {
+                time: _,
+                index: _,
+                baffle: _,
+                pre: _,
+                post: _,
+            } => None,
+            DataEvent::Escape {
+                time: _,
+                index: _,
+                pre: _,
+            } => None,
+        }
+    }
+
+    /// As `container_pressure`, but the collisions of tracer balls impart
+    /// nothing unless `include_tracers` is set.
+    pub fn container_pressure_of(&self, include_tracers: bool) -> Option<f64> {
+        match self {
+            DataEvent::ContainerCollision { pre, .. } if pre.is_tracer && !include_tracers => None,
+            _ => self.container_pressure(),
         }
     }
 
@@ -160,7 +248,82 @@ impl DataEvent {
                 index: _,
                 post: _,
                 pre: _,
+                normal: _,
             } => *time,
+            DataEvent::BaffleCollision {
+                time,
+                index: _,
+                baffle: _,
+                pre: _,
+                post: _,
+            } => *time,
+            DataEvent::Escape {
+                time,
+                index: _,
+                pre: _,
+            } => *time,
+        }
+    }
+
+    /// The indices of the participants in the collision. The second element is
+    /// `None` if the collision was with the container, including an escape
+    /// through the hole in it, or with a baffle.
+    pub fn participants(&self) -> (usize, Option<usize>) {
+        match self {
+            DataEvent::BallCollision {
+                time: _,
+                indices,
+                pres: _,
+                posts: _,
+            } => (indices.0, Some(indices.1)),
+            DataEvent::ContainerCollision {
+                time: _,
+                index,
+                pre: _,
+                post: _,
+                normal: _,
+            } => (*index, None),
+            DataEvent::BaffleCollision {
+                time: _,
+                index,
+                baffle: _,
+                pre: _,
+                post: _,
+            } => (*index, None),
+            DataEvent::Escape {
+                time: _,
+                index,
+                pre: _,
+            } => (*index, None),
+        }
+    }
+
+    /// A short name for the kind of event: `"ball"` for a collision between
+    /// two balls, `"container"` for a collision with the wall, `"baffle"` for a
+    /// collision with a baffle, and `"escape"` for a ball leaving through the
+    /// hole.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DataEvent::BallCollision { .. } => "ball",
+            DataEvent::ContainerCollision { .. } => "container",
+            DataEvent::BaffleCollision { .. } => "baffle",
+            DataEvent::Escape { .. } => "escape",
+        }
+    }
+
+    /// The velocities of the participants directly before and after the
+    /// collision, in the same order as `participants`. The container and
+    /// baffles are left out, and an escaped ball has no velocity afterwards.
+    pub fn velocities(&self) -> (Velocities, Velocities) {
+        let vel = |ball: &Ball| (ball.vel.x, ball.vel.y);
+        match self {
+            DataEvent::BallCollision { pres, posts, .. } => (
+                vec![vel(&pres.0), vel(&pres.1)],
+                vec![vel(&posts.0), vel(&posts.1)],
+            ),
+            DataEvent::ContainerCollision { pre, post, .. }
+            | DataEvent::BaffleCollision { pre, post, .. } => (vec![vel(pre)], vec![vel(post)]),
+            DataEvent::Escape { pre, .. } => (vec![vel(pre)], vec![]),
         }
     }
 }
@@ -1,11 +1,51 @@
 use crate::dynamics::maths::{approx_eq_f64, FloatVec};
+use pyo3::prelude::*;
+
+/// Events whose times are within this many units in the last place of each
+/// other are treated as simultaneous by `CollisionEvent::is_simultaneous`.
+/// The queue itself always orders events strictly by time, since a tolerance
+/// there would not be transitive.
+pub const EVENT_TIME_ULP: u64 = 1;
 
 #[derive(Clone, Copy)]
 pub enum CollisionPartner {
     Ball(usize),
     Container,
+    Baffle(usize),
+}
+
+/// The kind of partner a `Ball` collided with, as handed to Python, where the
+/// index of a partner `Ball` is given separately.
+#[pyclass]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PartnerKind {
+    Ball,
+    Container,
+    Baffle,
+}
+
+impl From<CollisionPartner> for PartnerKind {
+    fn from(partner: CollisionPartner) -> Self {
+        match partner {
+            CollisionPartner::Ball(_) => PartnerKind::Ball,
+            CollisionPartner::Container => PartnerKind::Container,
+            CollisionPartner::Baffle(_) => PartnerKind::Baffle,
+        }
+    }
+}
+
+impl CollisionPartner {
+    /// The index of the partner `Ball`, or `None` for the container or a
+    /// baffle.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            CollisionPartner::Ball(j) => Some(*j),
+            CollisionPartner::Container | CollisionPartner::Baffle(_) => None,
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct CollisionEvent {
     // Struct which identifies a collision between two `Ball`s within a
     // `Simulation`. `i` gives the index of the first ball involved in the
@@ -24,7 +64,7 @@ pub struct CollisionEvent {
 
 impl PartialEq for CollisionEvent {
     fn eq(&self, other: &Self) -> bool {
-        approx_eq_f64(self.t, other.t, 1)
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 
@@ -57,4 +97,22 @@ impl CollisionEvent {
     pub fn new(i: usize, j: CollisionPartner, t: f64, old_vels: (FloatVec, FloatVec)) -> Self {
         CollisionEvent { i, j, t, old_vels }
     }
+
+    pub fn time(&self) -> f64 {
+        self.t
+    }
+
+    pub fn participants(&self) -> (usize, CollisionPartner) {
+        (self.i, self.j)
+    }
+
+    pub fn old_vels(&self) -> (FloatVec, FloatVec) {
+        self.old_vels
+    }
+
+    /// Whether the two events happen at the same time, to within
+    /// `EVENT_TIME_ULP` units in the last place.
+    pub fn is_simultaneous(&self, other: &Self) -> bool {
+        approx_eq_f64(self.t, other.t, EVENT_TIME_ULP)
+    }
 }
@@ -0,0 +1,124 @@
+use crate::dynamics::DynamicsError;
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use super::{
+    collision_error,
+    simulate::{Simulation, SimulationCore},
+};
+
+type Worker = JoinHandle<(SimulationCore, Result<(), DynamicsError>)>;
+
+/// A run of collisions taking place on a background thread, as started by
+/// `Simulation.run_async`. The `SimulationCore` is moved onto the thread for
+/// the duration of the run, and handed back by `join`. Dropping the handle
+/// cancels the run and hands the state back too.
+#[pyclass]
+pub struct RunHandle {
+    worker: Option<Worker>,
+    target: Py<Simulation>,
+    radius: f64,
+    progress: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RunHandle {
+    pub fn spawn(mut sim: SimulationCore, target: Py<Simulation>, no_collisions: usize) -> Self {
+        // Run `sim` through `no_collisions` collisions on a new thread,
+        // stopping early if the run is cancelled. `target` is the Python
+        // object the state is returned to once the run is joined.
+        let radius = sim.container.r;
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let progress = Arc::clone(&progress);
+            let cancelled = Arc::clone(&cancelled);
+            thread::spawn(move || {
+                for _ in 0..no_collisions {
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if let Err(e) = sim.step_through_collision() {
+                        return (sim, Err(e));
+                    }
+                    progress.fetch_add(1, Ordering::Relaxed);
+                }
+                (sim, Ok(()))
+            })
+        };
+
+        RunHandle {
+            worker: Some(worker),
+            target,
+            radius,
+            progress,
+            cancelled,
+        }
+    }
+
+    fn restore(&mut self, py: Python<'_>) -> PyResult<()> {
+        // Wait for the run to finish and hand its state back to the target,
+        // then report how the run ended.
+        let worker = self
+            .worker
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("The run has already been joined."))?;
+        // The worker may need the GIL to evaluate a force field.
+        let Ok((sim, result)) = py.allow_threads(|| worker.join()) else {
+            // The state went down with the thread, so leave the target with
+            // an empty container of the same size, so it is usable again.
+            self.target.borrow_mut(py).core = Some(SimulationCore::new(self.radius));
+            return Err(PyRuntimeError::new_err("The run panicked."));
+        };
+        self.target.borrow_mut(py).core = Some(sim);
+        result.map_err(collision_error)
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        if self.worker.is_some() {
+            self.cancel();
+            Python::with_gil(|py| {
+                // There is no one left to report an error to, but the state
+                // is restored either way.
+                let _ = self.restore(py);
+            });
+        }
+    }
+}
+
+#[pymethods]
+impl RunHandle {
+    /// Whether the run has finished, either by completing, being cancelled,
+    /// or failing.
+    pub fn is_done(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_none_or(|worker| worker.is_finished())
+    }
+
+    /// The number of collisions completed so far.
+    pub fn progress(&self) -> usize {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /// Ask the run to stop after the collision in progress.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the run to finish, hand its state back to the simulation, and
+    /// return the number of collisions completed. A run can only be joined
+    /// once.
+    pub fn join(&mut self, py: Python<'_>) -> PyResult<usize> {
+        self.restore(py)?;
+        Ok(self.progress())
+    }
+}
@@ -1,3 +1,9 @@
+use pyo3::prelude::*;
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
 pub struct Histogram {
     left: f64,
     right: f64,
@@ -24,7 +30,81 @@ impl Histogram {
         }
     }
 
-    #[allow(dead_code)]
+    /// Bin the data arriving over `rx` as it arrives, dealing it out in turn
+    /// to `threads` worker threads and merging their partial histograms once
+    /// the channel hangs up, so the data is never held in memory all at once.
+    pub fn bin_parallel(
+        left: f64,
+        right: f64,
+        bins: usize,
+        rx: Receiver<f64>,
+        threads: usize,
+    ) -> Self {
+        thread::scope(|s| {
+            let (senders, workers): (Vec<_>, Vec<_>) = (0..threads.max(1))
+                .map(|_| {
+                    let (tx, worker_rx) = mpsc::channel();
+                    let worker = s.spawn(move || {
+                        Histogram::bin(left, right, bins, Box::new(worker_rx.into_iter()))
+                    });
+                    (tx, worker)
+                })
+                .unzip();
+            for (val, tx) in rx.into_iter().zip(senders.iter().cycle()) {
+                tx.send(val).unwrap();
+            }
+            // drop the senders to let the workers finish
+            drop(senders);
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .fold(Histogram::empty(left, right, bins), Histogram::merge)
+        })
+    }
+
+    /// Bin pairs of values into a `bins` by `bins` grid spanning `[left, right)`
+    /// in both dimensions. The counts are flattened row by row, so the count
+    /// for the pairs whose first value falls in bin `i` and second in bin `j` is
+    /// at `i * bins + j`. Pairs outside the grid are dropped.
+    pub fn bin_2d(
+        left: f64,
+        right: f64,
+        bins: usize,
+        data: impl Iterator<Item = (f64, f64)>,
+    ) -> Vec<usize> {
+        let width = (right - left) / (bins as f64);
+        let index = |val: f64| {
+            let val = val - left;
+            (val >= 0f64 && val < (right - left)).then(|| (val / width) as usize)
+        };
+        let mut counts = vec![0; bins * bins];
+        data.filter_map(|(a, b)| Some((index(a)?, index(b)?)))
+            .for_each(|(i, j)| counts[i * bins + j] += 1);
+        counts
+    }
+
+    pub fn empty(left: f64, right: f64, bins: usize) -> Self {
+        Histogram::bin(left, right, bins, Box::new(std::iter::empty()))
+    }
+
+    /// Combine two histograms with the same edges by summing their counts.
+    /// Panics if the edges differ.
+    pub fn merge(self, other: Histogram) -> Self {
+        assert!(
+            self.left == other.left && self.right == other.right && self.bins == other.bins,
+            "Cannot merge histograms with different edges."
+        );
+        let counts = self
+            .counts
+            .iter()
+            .zip(other.counts.iter())
+            .map(|(a, b)| a + b)
+            .collect();
+
+        Histogram { counts, ..self }
+    }
+
     pub fn edges(&self) -> Vec<f64> {
         let width = self.width();
         (0..=self.bins)
@@ -47,3 +127,74 @@ impl Histogram {
         self.counts.to_owned()
     }
 }
+
+/// The result of binning data into a `Histogram`, as handed back to Python.
+#[pyclass]
+pub struct HistogramResult {
+    #[pyo3(get)]
+    pub(crate) centres: Vec<f64>,
+    #[pyo3(get)]
+    pub(crate) edges: Vec<f64>,
+    #[pyo3(get)]
+    pub(crate) counts: Vec<usize>,
+    #[pyo3(get)]
+    pub(crate) width: f64,
+}
+
+impl From<Histogram> for HistogramResult {
+    fn from(hist: Histogram) -> Self {
+        HistogramResult {
+            centres: hist.centres(),
+            edges: hist.edges(),
+            counts: hist.counts(),
+            width: hist.width(),
+        }
+    }
+}
+
+#[pymethods]
+impl HistogramResult {
+    /// The counts normalised so that the histogram integrates to one. If the
+    /// histogram is empty, every bin has zero density.
+    pub fn density(&self) -> Vec<f64> {
+        let total: usize = self.counts.iter().sum();
+        if total == 0 {
+            return vec![0.; self.counts.len()];
+        }
+        let norm = total as f64 * self.width;
+        self.counts.iter().map(|&c| c as f64 / norm).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parallel_histogram() {
+        let data: Vec<f64> = (0..1_000).map(|i| (i as f64 * 0.37) % 1.).collect();
+        let single = Histogram::bin(0., 1., 20, Box::new(data.clone().into_iter()));
+
+        for threads in [0, 1, 4] {
+            let (tx, rx) = mpsc::channel();
+            data.iter().for_each(|&x| tx.send(x).unwrap());
+            drop(tx);
+            let parallel = Histogram::bin_parallel(0., 1., 20, rx, threads);
+            assert_eq!(single.counts(), parallel.counts());
+        }
+    }
+
+    #[test]
+    fn parallel_histogram_streams() {
+        // The histogram is built while the data is still being produced.
+        let (tx, rx) = mpsc::channel();
+        let producer = thread::spawn(move || {
+            for i in 0..10_000 {
+                tx.send((i % 10) as f64 + 0.5).unwrap();
+            }
+        });
+        let hist = Histogram::bin_parallel(0., 10., 10, rx, 3);
+        producer.join().unwrap();
+        assert_eq!(hist.counts(), vec![1_000; 10]);
+    }
+}
@@ -1,11 +1,25 @@
-use crate::dynamics::ball::Ball;
-use pyo3::{exceptions::PyValueError, prelude::*};
+use crate::dynamics::ball::{Ball, Container, LineSegment};
+use crate::dynamics::collide::Collide;
+use crate::dynamics::maths::FloatVec;
+use crate::dynamics::DynamicsError;
+use pyo3::{
+    exceptions::{PyIOError, PyIndexError, PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyType},
+};
+mod animation;
+use animation::Animation;
 mod data;
-mod event;
-mod histogram;
-use histogram::Histogram;
+use data::DataEvent;
+pub mod event;
+pub mod handle;
+use event::PartnerKind;
+use handle::RunHandle;
+pub mod histogram;
+use histogram::{Histogram, HistogramResult};
+use itertools::Itertools;
 pub mod simulate;
-use simulate::Simulation;
+use simulate::{Simulation, SimulationState};
 use std::{
     collections::{HashMap, VecDeque},
     sync::mpsc,
@@ -16,249 +30,4512 @@ use tqdm::tqdm;
 #[pymethods]
 impl Simulation {
     #[new]
-    fn py_new(radius: f64) -> Simulation {
-        Self::new(radius)
+    #[pyo3(signature = (radius, inner_radius=None))]
+    fn py_new(radius: f64, inner_radius: Option<f64>) -> PyResult<Simulation> {
+        let mut sim = Self::new(radius);
+        if let Some(inner) = inner_radius {
+            if !(0. < inner && inner < radius) {
+                return Err(PyValueError::new_err(
+                    "The inner radius must be positive and smaller than the outer radius.",
+                ));
+            }
+            sim.container = Container::annular(radius, inner);
+        }
+        Ok(sim)
+    }
+
+    /// Build a simulation of `n` identical balls of radius `ball_radius` in a
+    /// container of radius `radius`, placed at random without overlapping and
+    /// with Maxwell-Boltzmann velocities at `temperature` and zero total
+    /// momentum. The simulation is initialised, ready to run. `seed` seeds the
+    /// random number generator, so the construction is reproducible.
+    #[classmethod]
+    #[pyo3(name = "thermalized", signature = (radius, n, ball_radius, temperature, seed=0))]
+    fn py_thermalized(
+        _cls: &Bound<'_, PyType>,
+        radius: f64,
+        n: usize,
+        ball_radius: f64,
+        temperature: f64,
+        seed: u64,
+    ) -> PyResult<Simulation> {
+        Self::thermalized(radius, n, ball_radius, temperature, seed)
+            .ok_or_else(|| PyValueError::new_err("Could not fit the balls in the container."))
+    }
+
+    /// A line of `n` balls along the x-axis with small gaps between them,
+    /// where only the leftmost ball is moving, towards the others. The
+    /// simulation is initialised, ready to run.
+    #[classmethod]
+    #[pyo3(name = "newtons_cradle")]
+    fn py_newtons_cradle(_cls: &Bound<'_, PyType>, n: usize) -> PyResult<Simulation> {
+        Self::newtons_cradle(n)
+            .ok_or_else(|| PyValueError::new_err("Could not fit the balls in the container."))
     }
 
-    fn add_balls(&mut self, balls: Vec<Py<Ball>>) {
+    /// Two balls on the x-axis approaching each other, each with `speed`. The
+    /// simulation is initialised, ready to run.
+    #[classmethod]
+    #[pyo3(name = "head_on")]
+    fn py_head_on(_cls: &Bound<'_, PyType>, speed: f64) -> Simulation {
+        Self::head_on(speed)
+    }
+
+    /// Add the balls to the simulation. The collision queue is cleared, so
+    /// `initialise` must be called again before running.
+    fn add_balls(&mut self, balls: Vec<Py<Ball>>) -> PyResult<()> {
+        self.check_idle()?;
         Python::with_gil(|py| {
             for ball in balls {
                 let ball = ball.borrow(py).to_owned();
                 self.balls.push(ball);
             }
-        })
+        });
+        self.invalidate_queue();
+        Ok(())
     }
 
-    fn get_balls(&self) -> Vec<Ball> {
-        let mut out = Vec::new();
-        for ball in self.balls.iter() {
-            out.push(ball.clone())
-        }
-        out
+    /// Add a single ball to the simulation and return its index. The collision
+    /// queue is cleared, so `initialise` must be called again before running.
+    fn add_ball(&mut self, ball: Py<Ball>) -> PyResult<usize> {
+        self.check_idle()?;
+        Python::with_gil(|py| {
+            let ball = ball.borrow(py).to_owned();
+            self.balls.push(ball);
+        });
+        self.invalidate_queue();
+        Ok(self.balls.len() - 1)
     }
 
-    fn initialise(&mut self) {
-        // Based on the balls added to the container, initialise
-        // the dynamics of the `Simulation` so that the collision
-        // queue represents the correct dynamics.
-        self.generate_collision_queue();
-        self.generate_container_collisions();
+    /// Add a straight baffle from `a` to `b` inside the container, which the
+    /// balls bounce off elastically on either side, and return its index. The
+    /// collision queue is cleared, so `initialise` must be called again before
+    /// running.
+    fn add_baffle(&mut self, a: (f64, f64), b: (f64, f64)) -> PyResult<usize> {
+        self.check_idle()?;
+        if a == b {
+            return Err(PyValueError::new_err(
+                "A baffle must have a non-zero length.",
+            ));
+        }
+        self.baffles.push(LineSegment::new(a.into(), b.into()));
+        self.invalidate_queue();
+        Ok(self.baffles.len() - 1)
     }
 
-    #[pyo3(name = "next_collision")]
-    fn py_next_collision(&mut self) -> PyResult<()> {
-        self.step_through_collision()
-            .map_err(|_| PyValueError::new_err("Bad dynamics in the simulation."))
+    /// Replace all the balls in the simulation with `balls`. The per-ball
+    /// collision counts, the escape times and the count of simultaneous
+    /// collisions are reset, and the collision queue is cleared, so
+    /// `initialise` must be called again before running.
+    fn set_balls(&mut self, balls: Vec<Py<Ball>>) -> PyResult<()> {
+        self.check_idle()?;
+        self.balls = Python::with_gil(|py| {
+            balls
+                .into_iter()
+                .map(|ball| ball.borrow(py).to_owned())
+                .collect()
+        });
+        self.reset_ball_counters();
+        self.invalidate_queue();
+        Ok(())
     }
 
-    /// Run through `n` collisions, usually to thermalise the simulation.
-    #[pyo3(name = "thermalize", signature = (n, verbose=true))]
-    fn py_run_n_collisions(&mut self, n: usize, verbose: bool) -> PyResult<()> {
-        // TODO: rewrite the below as a macro
-        let mut norm;
-        let mut verb;
-        let it: &mut dyn Iterator<Item = usize> = if verbose {
-            println!("Running through collisions...");
-            verb = tqdm(0..n);
-            &mut verb
-        } else {
-            norm = 0..n;
-            &mut norm
+    /// Place stationary balls of radius `r` on a `rows` by `cols` lattice with
+    /// nearest-neighbour distance `spacing`, centred in the container. The
+    /// `lattice` may be `"square"` or `"triangular"`. Any balls that would fall
+    /// outside the container, or in the hole of an annulus, are skipped.
+    /// Return the number of balls placed.
+    /// The collision queue is cleared, so `initialise` must be called again
+    /// before running.
+    fn fill_lattice(
+        &mut self,
+        rows: usize,
+        cols: usize,
+        spacing: f64,
+        r: f64,
+        lattice: &str,
+    ) -> PyResult<usize> {
+        self.check_idle()?;
+        if spacing < 2. * r {
+            return Err(PyValueError::new_err(
+                "The lattice spacing is too small: the balls would overlap.",
+            ));
+        }
+        let (row_spacing, odd_row_offset) = match lattice {
+            "square" => (spacing, 0.),
+            "triangular" => (spacing * 3f64.sqrt() / 2., spacing / 2.),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown lattice: {}. Expected \"square\" or \"triangular\".",
+                    lattice
+                )))
+            }
         };
 
-        for _ in it {
-            self.py_next_collision()?;
+        let sites: Vec<FloatVec> = (0..rows)
+            .cartesian_product(0..cols)
+            .map(|(i, j)| {
+                let offset = if i % 2 == 1 { odd_row_offset } else { 0. };
+                FloatVec::new(j as f64 * spacing + offset, i as f64 * row_spacing)
+            })
+            .collect();
+        if sites.is_empty() {
+            return Ok(0);
         }
-        Ok(())
+        let centre = sites
+            .iter()
+            .fold(FloatVec::origin(), |acc, &site| acc + site)
+            / sites.len() as f64;
+
+        let balls: Vec<Ball> = sites
+            .into_iter()
+            .map(|site| Ball::new(self.container.centre + site - centre, FloatVec::origin(), r))
+            .filter(|ball| !self.outside_container(ball))
+            .collect();
+        if balls
+            .iter()
+            .any(|ball| self.balls.iter().any(|other| ball.overlapping(other, 0.)))
+        {
+            return Err(PyValueError::new_err(
+                "The lattice overlaps balls already in the simulation.",
+            ));
+        }
+
+        let placed = balls.len();
+        self.balls.extend(balls);
+        self.invalidate_queue();
+        Ok(placed)
     }
 
-    /// Run the simulation and record the pressure exerted on the walls of the
-    /// container by the colliding balls inside it. Return this as a Python
-    /// dictionary. This starts taking data immediately, so if it is run on an
-    /// un-thermalized simulation the results will be janky. `n` is the number
-    /// of collisions that will be recorded, and hence the simulation runtime
-    /// is proportional to `n`. `window_width` gives the width of the window
-    /// used for smooth averaging of the system pressure.
-    #[pyo3(name = "pressure")]
-    fn py_pressure(
-        &mut self,
-        n: usize,
-        window_width: usize,
-    ) -> PyResult<HashMap<String, PyObject>> {
-        let (mut time_deque, mut pressure_deque): (VecDeque<f64>, VecDeque<f64>) =
-            self.iter_pressure().take(window_width).collect();
+    /// The distance between the centres of balls `i` and `j` when they collide,
+    /// i.e. the sum of their radii, as used by `time_to_collision`.
+    fn collision_diameter(&self, i: usize, j: usize) -> PyResult<f64> {
+        self.check_idle()?;
+        let n = self.balls.len();
+        if i >= n || j >= n {
+            return Err(PyIndexError::new_err("Ball index out of range."));
+        }
+        Ok(self.balls[i].r + self.balls[j].r)
+    }
 
-        let pressure_events = self.iter_pressure();
-        let mut pressure_sum: f64 = pressure_deque.iter().sum();
-        let (times, pressures): (Vec<f64>, Vec<f64>) = pressure_events
-            .into_iter()
-            .take(n)
-            .map(|(time, pressure)| {
-                let old_pressure = pressure_deque.pop_front().unwrap();
-                time_deque.pop_front();
-                pressure_sum -= old_pressure;
-                pressure_sum += pressure;
+    /// The absolute time at which balls `i` and `j` will collide if neither
+    /// collides with anything else first, or `None` if they never will. This
+    /// is useful for checking the collision queue by hand.
+    fn pair_collision_time(&self, i: usize, j: usize) -> PyResult<Option<f64>> {
+        self.check_idle()?;
+        let n = self.balls.len();
+        if i >= n || j >= n {
+            return Err(PyIndexError::new_err("Ball index out of range."));
+        }
+        if i == j {
+            return Err(PyValueError::new_err("A ball cannot collide with itself."));
+        }
+        Ok(self.balls[i]
+            .time_to_collision(&self.balls[j])
+            .map(|t| self.reported_time(self.global_time + t)))
+    }
 
-                let t_start = time_deque.front().unwrap().clone();
-                let t_end = time;
-                time_deque.push_back(time);
-                pressure_deque.push_back(pressure);
-                (t_end - t_start, pressure_sum)
-            })
+    /// The largest radius of any ball, or `None` if there are no balls.
+    fn max_radius(&self) -> PyResult<Option<f64>> {
+        self.check_idle()?;
+        Ok(self.balls.iter().map(|ball| ball.r).max_by(f64::total_cmp))
+    }
+
+    /// The smallest radius of any ball, or `None` if there are no balls.
+    fn min_radius(&self) -> PyResult<Option<f64>> {
+        self.check_idle()?;
+        Ok(self.balls.iter().map(|ball| ball.r).min_by(f64::total_cmp))
+    }
+
+    /// The collision diameter averaged over all pairs of balls. Zero if there
+    /// are fewer than two balls.
+    fn mean_collision_diameter(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        let diameters: Vec<f64> = self
+            .balls
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| a.r + b.r)
             .collect();
+        Ok(if diameters.is_empty() {
+            0.
+        } else {
+            diameters.iter().sum::<f64>() / diameters.len() as f64
+        })
+    }
 
-        let dict_elements = Python::with_gil(|py| {
-            vec![
-                (String::from("times"), times.to_object(py)),
-                (String::from("pressures"), pressures.to_object(py)),
-            ]
-        });
+    fn ball_count(&self) -> PyResult<usize> {
+        self.check_idle()?;
+        Ok(self.balls.len())
+    }
 
-        let dict_map: HashMap<String, PyObject> = dict_elements.into_iter().collect();
-        Ok(dict_map)
+    fn get_balls(&self) -> PyResult<Vec<Ball>> {
+        self.check_idle()?;
+        let mut out = Vec::new();
+        for ball in self.balls.iter() {
+            out.push(ball.clone())
+        }
+        Ok(out)
     }
 
-    /// Run the simulation and record the times at which collisions take place,
-    /// aggregating them into a histogram which is returned in the form of a
-    /// Python dictionary that maps the bin centres to the counts. The system
-    /// must have previously been initialised, otherwise this is undefined.
-    fn collision_times(
+    /// A copy of the ball at `index`, without copying every other ball.
+    fn get_ball(&self, index: usize) -> PyResult<Ball> {
+        self.check_idle()?;
+        self.balls
+            .get(index)
+            .cloned()
+            .ok_or_else(|| PyIndexError::new_err("Ball index out of range."))
+    }
+
+    /// The payload carried by each ball. Payloads play no part in the
+    /// dynamics, so they can tag balls with any external quantity.
+    #[pyo3(name = "payloads")]
+    fn py_payloads(&self) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        Ok(self.payloads())
+    }
+
+    /// The indices of the balls sorted in ascending order of `key`, which is
+    /// one of `"speed"`, `"radius"`, `"x"` or `"y"`. The balls themselves are
+    /// not reordered.
+    fn balls_sorted_by(&self, key: &str) -> PyResult<Vec<usize>> {
+        self.check_idle()?;
+        let key: fn(&Ball) -> f64 = match key {
+            "speed" => |ball| ball.vel().magnitude(),
+            "radius" => |ball| ball.r,
+            "x" => |ball| ball.pos().x,
+            "y" => |ball| ball.pos().y,
+            _ => {
+                return Err(PyValueError::new_err(
+                    "Balls can only be sorted by speed, radius, x or y.",
+                ))
+            }
+        };
+        Ok((0..self.balls.len())
+            .sorted_by(|&i, &j| key(&self.balls[i]).total_cmp(&key(&self.balls[j])))
+            .collect())
+    }
+
+    /// Find all the pairs of balls whose centres are closer together than the
+    /// sum of their radii minus `tolerance`, i.e. that overlap.
+    #[pyo3(signature = (tolerance=0f64))]
+    fn overlapping_pairs(&self, tolerance: f64) -> PyResult<Vec<(usize, usize)>> {
+        self.check_idle()?;
+        Ok((0..self.balls.len())
+            .tuple_combinations()
+            .filter(|&(i, j)| self.balls[i].overlapping(&self.balls[j], tolerance))
+            .collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions, checking after
+    /// each whether any pair of balls overlaps by more than `tolerance`, as in
+    /// `overlapping_pairs`. Every such pair is recorded as `(i, j, time)`, so
+    /// this should come back empty: an overlap means the pair passed into each
+    /// other without a collision being resolved.
+    #[pyo3(signature = (no_collisions, tolerance=1e-9))]
+    fn detect_tunneling(
         &mut self,
         no_collisions: usize,
-        left: f64,
-        right: f64,
-        bins: usize,
-    ) -> PyResult<HashMap<String, PyObject>> {
-        let (tx_raw, rx_raw) = mpsc::channel();
-        let mut current_time = 0f64;
-
-        println!("Calculating collisions...");
-        for _ in tqdm(0..no_collisions) {
+        tolerance: f64,
+    ) -> PyResult<Vec<(usize, usize, f64)>> {
+        self.check_idle()?;
+        let mut tunneled = Vec::new();
+        for _ in 0..no_collisions {
             self.py_next_collision()?;
-            let collision_delta_t = self.global_time - current_time;
-            current_time = self.global_time;
-            tx_raw.send(collision_delta_t).unwrap();
+            tunneled.extend(
+                self.overlapping_pairs(tolerance)?
+                    .into_iter()
+                    .map(|(i, j)| (i, j, self.reported_time(self.global_time))),
+            );
         }
-        // drop the tx_raw to cause the channel to hang up
-        drop(tx_raw);
-
-        let (tx_hist, rx_hist) = mpsc::channel();
-        thread::spawn(move || {
-            let data = Box::new(rx_raw.into_iter());
-            let hist = Histogram::bin(left, right, bins, data);
-            tx_hist.send(hist).unwrap();
-        });
+        Ok(tunneled)
+    }
 
-        let hist = rx_hist.recv().unwrap();
-        let dict_elements = Python::with_gil(|py| {
-            vec![
-                (String::from("width"), hist.width().to_object(py)),
-                (String::from("centres"), hist.centres().to_object(py)),
-                (String::from("counts"), hist.counts().to_object(py)),
-            ]
-        });
+    /// Multiply the radius of every ball by `factor`, e.g. to sweep the
+    /// packing fraction without rebuilding the system. If this would leave any
+    /// balls overlapping each other or poking through a wall of the container,
+    /// the radii are left unchanged and a `ValueError` is raised. The collision
+    /// queue is cleared, so `initialise` must be called again before running.
+    fn scale_radii(&mut self, factor: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if factor <= 0. {
+            return Err(PyValueError::new_err("The scale factor must be positive."));
+        }
+        let old_radii: Vec<f64> = self.balls.iter().map(|ball| ball.r).collect();
+        for ball in self.balls.iter_mut() {
+            ball.r *= factor;
+        }
 
-        let dict_map: HashMap<String, PyObject> = dict_elements.into_iter().collect();
-        Ok(dict_map)
+        let poking_through = self.balls.iter().any(|ball| self.outside_container(ball));
+        if poking_through || !self.overlapping_pairs(0.)?.is_empty() {
+            for (ball, r) in self.balls.iter_mut().zip(old_radii) {
+                ball.r = r;
+            }
+            return Err(PyValueError::new_err(
+                "Scaling the radii would make the balls overlap or leave the container.",
+            ));
+        }
+        self.invalidate_queue();
+        Ok(())
     }
 
-    /// Run the simulation and track the positions of the balls. Panic in the
-    /// secondary thread when a ball ends up outside the container and give the
-    /// collision number and the global time at which it happened.
-    fn track_positions(&mut self, no_collisions: usize) -> PyResult<()> {
-        let (tx, rx) = mpsc::channel();
+    /// Multiply the radius of the container by `factor`, along with the inner
+    /// radius of an annulus, to sweep the density at fixed velocities. If
+    /// `scale_positions` is set, the centres of the balls are moved towards or
+    /// away from the centre of the container by the same factor, so they keep
+    /// their places relative to it. If this would leave any balls overlapping
+    /// each other or poking through a wall, nothing is changed and a
+    /// `ValueError` is raised. The collision queue is cleared, so `initialise`
+    /// must be called again before running.
+    fn scale_container(&mut self, factor: f64, scale_positions: bool) -> PyResult<()> {
+        self.check_idle()?;
+        if factor <= 0. {
+            return Err(PyValueError::new_err("The scale factor must be positive."));
+        }
+        let (old_r, old_inner) = (self.container.r, self.container.inner_radius);
+        let old_positions: Vec<FloatVec> = self.balls.iter().map(|ball| ball.pos).collect();
+        self.container.r *= factor;
+        self.container.inner_radius = old_inner.map(|inner| inner * factor);
+        if scale_positions {
+            let centre = self.container.centre;
+            for ball in self.balls.iter_mut() {
+                ball.pos = centre + (ball.pos - centre) * factor;
+            }
+        }
 
-        println!("Calculating collisions...");
-        for i in tqdm(0..no_collisions) {
-            self.py_next_collision()?;
-            let r_squareds: Vec<f64> = self
-                .balls
-                .iter()
-                .map(|ball| ball.pos().dot(ball.pos()))
-                .collect();
-            let global_time = self.global_time;
-            tx.send((i, global_time, r_squareds)).unwrap();
+        let poking_through = self.balls.iter().any(|ball| self.outside_container(ball));
+        if poking_through || !self.overlapping_pairs(0.)?.is_empty() {
+            self.container.r = old_r;
+            self.container.inner_radius = old_inner;
+            for (ball, pos) in self.balls.iter_mut().zip(old_positions) {
+                ball.pos = pos;
+            }
+            return Err(PyValueError::new_err(
+                "Scaling the container would make the balls overlap or leave it.",
+            ));
         }
-        // drop the tx_raw to cause the channel to hang up
-        drop(tx);
+        self.invalidate_queue();
+        Ok(())
+    }
 
-        let checker = thread::spawn(move || {
-            for info in rx {
-                let (i, global_time, r_squareds) = info;
-                for r_squared in r_squareds {
-                    if r_squared > 1f64 {
-                        panic!("Collision: {};\tGlobal time: {}", i, global_time);
-                    }
+    /// The smallest clearance between any two balls, i.e. the distance between
+    /// their centres minus the sum of their radii. A negative clearance means
+    /// the pair overlaps. `None` if there are fewer than two balls.
+    fn min_pair_distance(&self) -> PyResult<Option<f64>> {
+        self.check_idle()?;
+        Ok(self
+            .balls
+            .iter()
+            .tuple_combinations()
+            .map(|(p, q)| (*p.pos() - *q.pos()).magnitude() - p.r - q.r)
+            .min_by(f64::total_cmp))
+    }
+
+    /// The smallest clearance between any ball and a wall of the container,
+    /// including the inner wall of an annulus. A negative clearance means the
+    /// ball pokes through the wall. `None` if there are no balls.
+    fn min_wall_distance(&self) -> PyResult<Option<f64>> {
+        self.check_idle()?;
+        let outer = self.container.r;
+        let inner = self.container.inner_radius;
+        Ok(self
+            .balls
+            .iter()
+            .map(|ball| {
+                let distance = self.container.offset(*ball.pos()).magnitude();
+                let outer_clearance = outer - distance - ball.r;
+                match inner {
+                    Some(inner) => outer_clearance.min(distance - ball.r - inner),
+                    None => outer_clearance,
                 }
-            }
-        });
+            })
+            .min_by(f64::total_cmp))
+    }
 
-        checker.join().unwrap();
+    /// Nudge the position of ball `index` by `dpos` and its velocity by `dvel`,
+    /// e.g. to study how quickly nearby trajectories diverge. If the ball would
+    /// end up poking through the container or overlapping another ball, it is
+    /// left unchanged and a `ValueError` is raised. The collision queue is
+    /// cleared, so `initialise` must be called again before running.
+    fn perturb_ball(&mut self, index: usize, dpos: (f64, f64), dvel: (f64, f64)) -> PyResult<()> {
+        self.check_idle()?;
+        if index >= self.balls.len() {
+            return Err(PyIndexError::new_err("Ball index out of range."));
+        }
+        let mut ball = self.balls[index].clone();
+        ball.pos += dpos.into();
+        ball.set_vel(*ball.vel() + dvel.into());
 
+        let overlapping = self
+            .balls
+            .iter()
+            .enumerate()
+            .any(|(i, other)| i != index && ball.overlapping(other, 0.));
+        if overlapping || self.outside_container(&ball) {
+            return Err(PyValueError::new_err(
+                "The perturbed ball would overlap another or leave the container.",
+            ));
+        }
+        self.balls[index] = ball;
+        self.invalidate_queue();
         Ok(())
     }
 
-    /// Run the simulation and record the times between which `n` collisions
-    /// take place. Aggregate the data into a histogram.  The system must have
-    /// previously been initialised, otherwise this is undefined.
-    fn nth_collision_times(
-        &mut self,
-        n: usize,
-        no_collisions: usize,
-        left: f64,
-        right: f64,
-        bins: usize,
-    ) -> PyResult<HashMap<String, PyObject>> {
-        let (tx_raw, rx_raw) = mpsc::channel();
-        let mut current_time = 0f64;
+    fn initialise(&mut self) -> PyResult<()> {
+        self.check_idle()?;
+        // Based on the balls added to the container, initialise
+        // the dynamics of the `Simulation` so that the collision
+        // queue represents the correct dynamics.
+        self.generate_collision_queue();
+        self.generate_container_collisions();
+        self.initialised = true;
+        Ok(())
+    }
 
-        println!("Calculating collisions...");
-        for _ in tqdm(0..no_collisions / n) {
-            let mut local_sum = 0f64;
-            for _ in 0..n {
-                self.py_next_collision()?;
-                let collision_delta_t = self.global_time - current_time;
-                current_time = self.global_time;
-                local_sum += collision_delta_t;
-            }
-            tx_raw.send(local_sum).unwrap();
+    /// Negate the velocity of every ball, so that the simulation retraces its
+    /// path. The collision queue is cleared, so `initialise` must be called
+    /// again before running. The container is static, so it is unaffected.
+    ///
+    /// The reversal is only approximate: every step is shortened by the
+    /// `delta` fudge factor, so balls stop just short of contact both on the
+    /// way out and on the way back, and the errors accumulate rather than
+    /// cancel. Because the queue is rebuilt from scratch, the velocity-based
+    /// validation of collision events does not otherwise spoil reversibility.
+    fn reverse_velocities(&mut self) -> PyResult<()> {
+        self.check_idle()?;
+        for ball in self.balls.iter_mut() {
+            ball.set_vel(ball.vel * -1.);
         }
-        // drop the tx_raw to cause the channel to hang up
-        drop(tx_raw);
+        self.invalidate_queue();
+        Ok(())
+    }
 
-        let (tx_hist, rx_hist) = mpsc::channel();
-        thread::spawn(move || {
-            let data = Box::new(rx_raw.into_iter());
-            let hist = Histogram::bin(left, right, bins, data);
-            tx_hist.send(hist).unwrap();
-        });
+    /// Transform the velocities of the balls into the centre-of-mass frame by
+    /// subtracting the mean velocity from each. Frozen balls are excluded as
+    /// they have infinite mass. The collision queue is cleared, so
+    /// `initialise` must be called again before running.
+    #[pyo3(name = "to_com_frame")]
+    fn py_to_com_frame(&mut self) -> PyResult<()> {
+        self.check_idle()?;
+        let com_velocity = self.com_velocity();
+        for ball in self.balls.iter_mut().filter(|ball| !ball.frozen) {
+            ball.set_vel(ball.vel - com_velocity);
+        }
+        self.invalidate_queue();
+        Ok(())
+    }
 
-        let hist = rx_hist.recv().unwrap();
-        let dict_elements = Python::with_gil(|py| {
-            vec![
-                (String::from("width"), hist.width().to_object(py)),
-                (String::from("centres"), hist.centres().to_object(py)),
-                (String::from("counts"), hist.counts().to_object(py)),
-            ]
-        });
+    /// The position of the centre of mass of the (unfrozen) balls, so that the
+    /// lab frame can be reconstructed after `to_com_frame`.
+    fn com_position(&self) -> PyResult<(f64, f64)> {
+        self.check_idle()?;
+        let com = self.centre_of_mass();
+        Ok((com.x, com.y))
+    }
 
-        let dict_map: HashMap<String, PyObject> = dict_elements.into_iter().collect();
-        Ok(dict_map)
+    /// The total momentum of the (unfrozen) balls, which all have unit mass.
+    #[pyo3(name = "total_momentum")]
+    fn py_total_momentum(&self) -> PyResult<(f64, f64)> {
+        self.check_idle()?;
+        let momentum = self.total_momentum();
+        Ok((momentum.x, momentum.y))
     }
-}
 
-// fn run_later(n: usize, verbose: bool) -> impl Iterator<Item = usize> {
-//     let mut progress = None;
-//     let mut regular = None;
+    /// The magnitude of the total momentum of the (unfrozen) balls. This should
+    /// be zero after `to_com_frame`.
+    fn momentum_magnitude(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.total_momentum().magnitude())
+    }
 
-//     if verbose {
-//         progress = Some(tqdm(0..n));
-//     } else {
-//         regular = Some(0..n)
-//     }
+    /// The direction of the total momentum of the (unfrozen) balls, as an angle
+    /// anticlockwise from the x-axis in radians, or `None` if the total
+    /// momentum is zero.
+    fn momentum_direction(&self) -> PyResult<Option<f64>> {
+        self.check_idle()?;
+        let momentum = self.total_momentum();
+        Ok(if momentum.magnitude() == 0. {
+            None
+        } else {
+            Some(momentum.y.atan2(momentum.x))
+        })
+    }
 
-//     progress
-//         .into_iter()
-//         .flatten()
-//         .chain(regular.into_iter().flatten())
-// }
+    #[pyo3(name = "next_collision")]
+    fn py_next_collision(&mut self) -> PyResult<()> {
+        self.check_idle()?;
+        self.step_through_collision().map_err(collision_error)
+    }
+
+    /// The number of collisions so far that followed another involving the
+    /// same ball at the same moment, as when a ball strikes a row of touching
+    /// balls. The balls in such a multi-ball contact are collided a pair at a
+    /// time, in an order set by tie-breaking, which can be unphysical.
+    fn simultaneous_collisions_detected(&self) -> PyResult<usize> {
+        self.check_idle()?;
+        Ok(self.simultaneous_collisions)
+    }
+
+    /// Run through `n` collisions and return the number completed. If the run
+    /// fails partway, the error message reports how many collisions succeeded
+    /// before the failure.
+    #[pyo3(name = "run_collisions_counted")]
+    fn py_run_collisions_counted(&mut self, n: usize) -> PyResult<usize> {
+        self.check_idle()?;
+        self.run_collisions_counted(n)
+            .map_err(|(completed, e)| match e {
+                DynamicsError::TimeLimitExceeded => PyRuntimeError::new_err(format!(
+                    "The simulation reached its maximum time after {} of {} collisions.",
+                    completed, n
+                )),
+                DynamicsError::Uninitialised => collision_error(e),
+                _ => PyValueError::new_err(format!(
+                    "Bad dynamics in the simulation after {} of {} collisions: {:?}.",
+                    completed, n, e
+                )),
+            })
+    }
+
+    /// Replay a recorded sequence of collisions against the current state,
+    /// bypassing the collision queue. Each event is a dict with the `time` of
+    /// the collision and the indices `i` and `j` of the balls involved, with
+    /// `j` as `None` for a collision with the container. If the participants of
+    /// an event are not within `tolerance` of touching when it is replayed, a
+    /// `ValueError` identifies the event. The `delta` fudge factor leaves balls
+    /// just short of contact, so the tolerance cannot be made arbitrarily
+    /// small. Afterwards the simulation can carry on running from the end of
+    /// the replay.
+    #[pyo3(name = "replay", signature = (events, tolerance=1e-4))]
+    fn py_replay<'py>(&mut self, events: Vec<Bound<'py, PyDict>>, tolerance: f64) -> PyResult<()> {
+        self.check_idle()?;
+        let field = |event: &Bound<'py, PyDict>, key: &str| -> PyResult<Bound<'py, PyAny>> {
+            event
+                .get_item(key)?
+                .ok_or_else(|| PyValueError::new_err(format!("Event is missing `{}`.", key)))
+        };
+        let events = events
+            .iter()
+            .map(|event| {
+                let time: f64 = field(event, "time")?.extract()?;
+                let i: usize = field(event, "i")?.extract()?;
+                let j: Option<usize> = field(event, "j")?.extract()?;
+                Ok((time, i, j))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        self.replay(&events, tolerance).map_err(|e| match e {
+            DynamicsError::ReplayMismatch { event } => PyValueError::new_err(format!(
+                "Replayed event {} does not match the state of the simulation.",
+                event
+            )),
+            e => collision_error(e),
+        })
+    }
+
+    /// The next collision scheduled for ball `index`, as its time, the
+    /// `partner` it is with as a `PartnerKind`, and the index of the partner
+    /// ball, which is `None` for the container or a baffle. `None` if nothing
+    /// is scheduled for it. Raises a `RuntimeError` if the simulation has not
+    /// been initialised, since there is no queue to look in.
+    #[pyo3(name = "ball_next_collision")]
+    fn py_ball_next_collision(
+        &self,
+        index: usize,
+    ) -> PyResult<Option<(f64, PartnerKind, Option<usize>)>> {
+        self.check_idle()?;
+        self.check_initialised()?;
+        if index >= self.balls.len() {
+            return Err(PyIndexError::new_err("Ball index out of range."));
+        }
+        Ok(self
+            .ball_next_collision(index)
+            .map(|(t, partner)| (t, partner.into(), partner.index())))
+    }
+
+    /// Run the simulation up to time `t`, and return the number of collisions
+    /// with the container that occurred alongside the time elapsed during the
+    /// run, so that the pressure can be normalised by hand.
+    #[pyo3(name = "run_until_time")]
+    fn py_run_until_time(&mut self, t: f64) -> PyResult<(usize, f64)> {
+        self.check_idle()?;
+        let start = self.global_time;
+        let container_collisions = self.run_until_time(t).map_err(collision_error)?;
+        Ok((
+            container_collisions,
+            self.reported_time(self.global_time - start),
+        ))
+    }
+
+    /// Run the simulation for `duration`, writing an animation of it to `path`
+    /// as JSON: `{"container": {"r", "inner_radius", "centre"}, "radii": [...],
+    /// "frames": [{"t", "positions": [...]}]}`. Frames are taken `fps` times
+    /// per unit time, starting from the current state, so there are
+    /// `duration * fps` of them. `duration` and `fps` are in simulation units,
+    /// while the frame times are reported in the units set by `set_time_scale`.
+    fn export_animation_json(&mut self, path: &str, duration: f64, fps: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if !(duration > 0. && fps > 0.) {
+            return Err(PyValueError::new_err(
+                "The duration and frame rate must be positive.",
+            ));
+        }
+        let start = self.global_time;
+        let no_frames = (duration * fps).round() as usize;
+        let mut animation = Animation::new(self);
+        for k in 0..no_frames {
+            self.run_until_time(start + k as f64 / fps)
+                .map_err(collision_error)?;
+            animation.push_frame(self);
+        }
+
+        let file = std::fs::File::create(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), &animation)
+            .map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    /// Run the simulation up to and including the next collision of a ball with
+    /// the container, and return the index of that ball. If no ball ever
+    /// collides with the container, an error is raised.
+    #[pyo3(name = "run_until_wall")]
+    fn py_run_until_wall(&mut self) -> PyResult<usize> {
+        self.check_idle()?;
+        self.run_until_wall().map_err(|e| match e {
+            DynamicsError::SimulationFailure => {
+                PyRuntimeError::new_err("No ball will collide with the container.")
+            }
+            e => collision_error(e),
+        })
+    }
+
+    /// The temperature of the balls in the simulation, assuming they all have
+    /// unit mass and obey equipartition. Tracer balls are left out if
+    /// `include_tracers` is false.
+    #[pyo3(name = "temperature", signature = (include_tracers=true))]
+    fn py_temperature(&self, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.temperature_of(include_tracers))
+    }
+
+    /// The total kinetic energy of the balls, which all have unit mass. Tracer
+    /// balls are left out if `include_tracers` is false.
+    #[pyo3(signature = (include_tracers=true))]
+    fn total_kinetic_energy(&self, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.kinetic_energy_of(include_tracers))
+    }
+
+    /// The minimum, maximum, mean and rms speeds of the balls, computed in a
+    /// single pass and returned as a dict with keys `min`, `max`, `mean` and
+    /// `rms`. Every value is zero if there are no balls. Tracer balls are left
+    /// out if `include_tracers` is false.
+    #[pyo3(signature = (include_tracers=true))]
+    fn speed_stats(&self, include_tracers: bool) -> PyResult<HashMap<String, f64>> {
+        self.check_idle()?;
+        let (mut min, mut max, mut sum, mut sum_squares) = (f64::INFINITY, 0f64, 0., 0.);
+        let mut count = 0;
+        for ball in self.observed_balls(include_tracers) {
+            count += 1;
+            let speed_squared = ball.vel().dot(ball.vel());
+            let speed = speed_squared.sqrt();
+            min = min.min(speed);
+            max = max.max(speed);
+            sum += speed;
+            sum_squares += speed_squared;
+        }
+
+        let n = count as f64;
+        let stats = if count == 0 {
+            [0.; 4]
+        } else {
+            [min, max, sum / n, (sum_squares / n).sqrt()]
+        };
+        Ok(["min", "max", "mean", "rms"]
+            .into_iter()
+            .map(String::from)
+            .zip(stats)
+            .collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions, recording the
+    /// total kinetic energy at `samples` evenly spaced collision counts, the
+    /// last of them at the end of the run, and return its mean and population
+    /// variance, alongside the series itself if `return_series` is set. For a
+    /// microcanonical run the variance is related to the heat capacity; with no
+    /// forces acting it should vanish, since elastic collisions conserve the
+    /// kinetic energy. Tracer balls are left out of the energy if
+    /// `include_tracers` is false.
+    #[pyo3(signature = (no_collisions, samples, return_series=false, include_tracers=true))]
+    fn energy_fluctuations(
+        &mut self,
+        no_collisions: usize,
+        samples: usize,
+        return_series: bool,
+        include_tracers: bool,
+    ) -> PyResult<(f64, f64, Option<Vec<f64>>)> {
+        self.check_idle()?;
+        if samples == 0 || samples > no_collisions {
+            return Err(PyValueError::new_err(
+                "There must be between one sample and one per collision.",
+            ));
+        }
+        let mut energies = Vec::with_capacity(samples);
+        let mut done = 0;
+        for k in 1..=samples {
+            while done < k * no_collisions / samples {
+                self.py_next_collision()?;
+                done += 1;
+            }
+            energies.push(self.kinetic_energy_of(include_tracers));
+        }
+
+        let n = energies.len() as f64;
+        let mean = energies.iter().sum::<f64>() / n;
+        let variance = energies.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / n;
+        Ok((mean, variance, return_series.then_some(energies)))
+    }
+
+    /// The `mean`, `variance`, `skewness` and excess `kurtosis` of the speeds
+    /// of the balls, as a dict. These are population moments, and can be
+    /// compared with the values for a 2D Maxwell-Boltzmann (Rayleigh)
+    /// distribution: a skewness of about 0.631 and an excess kurtosis of about
+    /// 0.245. At least four balls with different speeds are needed. Tracer
+    /// balls are left out if `include_tracers` is false.
+    #[pyo3(signature = (include_tracers=true))]
+    fn velocity_moments(&self, include_tracers: bool) -> PyResult<HashMap<String, f64>> {
+        self.check_idle()?;
+        let speeds: Vec<f64> = self
+            .observed_balls(include_tracers)
+            .map(|ball| ball.vel().magnitude())
+            .collect();
+        let n = speeds.len() as f64;
+        let mean = speeds.iter().sum::<f64>() / n;
+        let moment = |k: i32| speeds.iter().map(|s| (s - mean).powi(k)).sum::<f64>() / n;
+        let variance = moment(2);
+        if speeds.len() < 4 || variance == 0. {
+            return Err(PyValueError::new_err(
+                "The moments need at least four balls with different speeds.",
+            ));
+        }
+        let skewness = moment(3) / variance.powf(1.5);
+        let kurtosis = moment(4) / (variance * variance) - 3.;
+        Ok(["mean", "variance", "skewness", "kurtosis"]
+            .into_iter()
+            .map(String::from)
+            .zip([mean, variance, skewness, kurtosis])
+            .collect())
+    }
+
+    /// The kinetic energy of the balls split into its x and y parts,
+    /// `(sum 0.5 m vx^2, sum 0.5 m vy^2)`. By equipartition these are equal at
+    /// equilibrium, so a large imbalance signals that the system is not yet
+    /// isotropic. Tracer balls are left out if `include_tracers` is false.
+    #[pyo3(name = "energy_components", signature = (include_tracers=true))]
+    fn py_energy_components(&self, include_tracers: bool) -> PyResult<(f64, f64)> {
+        self.check_idle()?;
+        Ok(self.energy_components_of(include_tracers))
+    }
+
+    /// Run collisions until the kinetic energy is shared evenly between the x
+    /// and y directions, `|Ex - Ey| / (Ex + Ey) < tolerance`, and return the
+    /// number of collisions and the time this took. Raises an error if the
+    /// balls are not isotropic after `max_collisions` collisions.
+    fn isotropization_time(
+        &mut self,
+        tolerance: f64,
+        max_collisions: usize,
+    ) -> PyResult<(usize, f64)> {
+        self.check_idle()?;
+        let start = self.global_time;
+        let isotropic = |sim: &Simulation| {
+            let (ex, ey) = sim.energy_components();
+            (ex - ey).abs() < tolerance * (ex + ey)
+        };
+        for count in 0..=max_collisions {
+            if isotropic(self) {
+                return Ok((count, self.reported_time(self.global_time - start)));
+            }
+            if count < max_collisions {
+                self.py_next_collision()?;
+            }
+        }
+        Err(PyRuntimeError::new_err(
+            "The balls did not become isotropic within the collision limit.",
+        ))
+    }
+
+    /// The mean speed of the balls, `<|v|>`. Zero if there are no balls.
+    /// Tracer balls are left out if `include_tracers` is false.
+    #[pyo3(name = "mean_speed", signature = (include_tracers=true))]
+    fn py_mean_speed(&self, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.mean_speed_of(include_tracers))
+    }
+
+    /// The root-mean-square speed of the balls, `sqrt(<v^2>)`. For a
+    /// two-dimensional Maxwell-Boltzmann distribution this exceeds the mean
+    /// speed by a factor of `2 / sqrt(pi)`. Zero if there are no balls.
+    /// Tracer balls are left out if `include_tracers` is false.
+    #[pyo3(name = "rms_speed", signature = (include_tracers=true))]
+    fn py_rms_speed(&self, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.rms_speed_of(include_tracers))
+    }
+
+    /// The area of the container available to the balls.
+    fn area(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.container.area())
+    }
+
+    /// The total length of the walls of the container.
+    fn perimeter(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.container.perimeter())
+    }
+
+    /// The pressure predicted by the ideal gas law, `P = N k_B T / A`, for the
+    /// current state of the simulation. Useful to validate the measured
+    /// pressure against. Tracer balls are left out if `include_tracers` is
+    /// false.
+    #[pyo3(name = "ideal_pressure", signature = (include_tracers=true))]
+    fn py_ideal_pressure(&self, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.ideal_pressure_of(include_tracers))
+    }
+
+    /// Subject the balls to an external force field, `force(x, y) -> (fx, fy)`,
+    /// between collisions. The motion is integrated in fixed sub-steps of
+    /// length `substep`, so the simulation is no longer exactly event-driven
+    /// and collisions are only resolved approximately. Pass `None` to turn the
+    /// force field off again.
+    #[pyo3(name = "set_force_field", signature = (force, substep=1e-3))]
+    fn py_set_force_field(&mut self, force: Option<PyObject>, substep: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if substep <= 0. {
+            return Err(PyValueError::new_err("The sub-step must be positive."));
+        }
+        self.set_force(force, substep);
+        Ok(())
+    }
+
+    /// The value of the Boltzmann constant used by the simulation. By default
+    /// the simulation works in reduced units, where `k_B = 1`.
+    #[pyo3(name = "get_k_b")]
+    fn py_get_k_b(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.k_b())
+    }
+
+    /// Set the value of the Boltzmann constant used by the simulation, so that
+    /// temperatures and pressures can be reported in physical units.
+    #[pyo3(name = "set_k_b")]
+    fn py_set_k_b(&mut self, value: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if value <= 0. {
+            return Err(PyValueError::new_err(
+                "The Boltzmann constant must be positive.",
+            ));
+        }
+        self.set_k_b(value);
+        Ok(())
+    }
+
+    /// The current time of the simulation in the units set by
+    /// `set_time_scale`. `global_time` is always in simulation units.
+    fn time(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.reported_time(self.global_time))
+    }
+
+    /// The current time of the simulation in simulation units.
+    #[getter(global_time)]
+    fn py_get_global_time(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.global_time)
+    }
+
+    /// The length of one unit of simulation time in the units that times are
+    /// reported in.
+    #[pyo3(name = "get_time_scale")]
+    fn py_get_time_scale(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.time_scale())
+    }
+
+    /// Set the length of one unit of simulation time in physical units, e.g.
+    /// seconds, so that times are reported in those units. This applies to
+    /// `time`, `event_times`, `collision_times`, `free_flight_times`,
+    /// `wall_impulses`, `run_to_columns`, `next_collision_data`,
+    /// `run_until_time`, `isotropization_time`, `pair_collision_time`,
+    /// `first_wall_times`, `detect_tunneling`, `escape_times`,
+    /// `nth_collision_times`, the window lengths of `pressure` and the frame
+    /// times of `export_animation_json`. Times passed in, and those of
+    /// `collision_events`, which are fed back to `replay`, stay in simulation
+    /// units. Pressures, speeds, energies and viscosities also stay in
+    /// simulation units: they carry the simulation's velocity unit too, so
+    /// rescaling only their time part would mix the two systems of units.
+    #[pyo3(name = "set_time_scale")]
+    fn py_set_time_scale(&mut self, value: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if value <= 0. {
+            return Err(PyValueError::new_err("The time scale must be positive."));
+        }
+        self.set_time_scale(value);
+        Ok(())
+    }
+
+    /// Attract the balls towards the centre of the container with a harmonic
+    /// force, `F = -k r`. Between sub-steps (see `set_force_field`) the balls
+    /// move exactly along their elliptical orbits, but collisions are still
+    /// only predicted approximately. Every sub-step and every collision
+    /// predicts all pairs of balls afresh, so the cost grows as the square of
+    /// the number of balls. Pass `None` to turn the force off again.
+    #[pyo3(name = "set_central_harmonic")]
+    fn py_set_central_harmonic(&mut self, k: Option<f64>) -> PyResult<()> {
+        self.check_idle()?;
+        if k.is_some_and(|k| k <= 0.) {
+            return Err(PyValueError::new_err(
+                "The spring constant must be positive.",
+            ));
+        }
+        self.set_central_harmonic(k);
+        Ok(())
+    }
+
+    /// Capture the state of the simulation, including its collision queue, as
+    /// nested tuples of plain numbers that can be pickled. Loading the state
+    /// with `load_state` resumes the run exactly, which re-initialising the
+    /// queue would not, because of the `delta` fudge factor.
+    #[pyo3(name = "save_state")]
+    fn py_save_state(&self) -> PyResult<SimulationState> {
+        self.check_idle()?;
+        Ok(self.save_state())
+    }
+
+    /// Restore a state captured by `save_state`. The container, baffles and
+    /// parameters of this simulation are kept, so it should be constructed the
+    /// same way as the one that was saved.
+    #[pyo3(name = "load_state")]
+    fn py_load_state(&mut self, state: SimulationState) -> PyResult<()> {
+        self.check_idle()?;
+        self.load_state(state).map_err(|_| {
+            PyValueError::new_err(
+                "The state refers to balls or baffles that this simulation does not contain.",
+            )
+        })
+    }
+
+    /// The centres of all the balls packed into a compact buffer of
+    /// little-endian `f64`s, `x` then `y` for each ball in turn, so `16 * n`
+    /// bytes for `n` balls. This is cheap to append to a binary trajectory
+    /// file; `positions_from_bytes` unpacks it. If `precision` is given, the
+    /// positions are rounded to that many decimal places first, so that the
+    /// file compresses far better.
+    #[pyo3(signature = (precision=None))]
+    fn snapshot_positions_bytes(&self, precision: Option<u32>) -> PyResult<Vec<u8>> {
+        self.check_idle()?;
+        Ok(self
+            .balls
+            .iter()
+            .map(|ball| match precision {
+                Some(decimals) => ball.pos().round_to(decimals),
+                None => *ball.pos(),
+            })
+            .flat_map(|pos| [pos.x, pos.y])
+            .flat_map(f64::to_le_bytes)
+            .collect())
+    }
+
+    /// Unpack a buffer written by `snapshot_positions_bytes` into the centres
+    /// of the balls.
+    #[staticmethod]
+    fn positions_from_bytes(bytes: Vec<u8>) -> PyResult<Vec<(f64, f64)>> {
+        if !bytes.len().is_multiple_of(16) {
+            return Err(PyValueError::new_err(
+                "The buffer must hold a whole number of positions.",
+            ));
+        }
+        let value = |chunk: &[u8]| f64::from_le_bytes(chunk.try_into().unwrap());
+        Ok(bytes
+            .chunks_exact(16)
+            .map(|chunk| (value(&chunk[..8]), value(&chunk[8..])))
+            .collect())
+    }
+
+    /// Open a hole in the wall of the container, spanning `half_width` radians
+    /// either side of the angle `center_angle`, measured anticlockwise from the
+    /// x-axis. Balls that reach the hole escape and are removed from the
+    /// simulation, so the indices of the balls after them shift down. Only the
+    /// outer wall of an annulus has the hole.
+    #[pyo3(name = "set_hole")]
+    fn py_set_hole(&mut self, center_angle: f64, half_width: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if half_width <= 0. {
+            return Err(PyValueError::new_err(
+                "The half-width of the hole must be positive.",
+            ));
+        }
+        self.set_hole(Some((center_angle, half_width)));
+        Ok(())
+    }
+
+    /// The number of balls that have escaped through the hole.
+    fn escaped_count(&self) -> PyResult<usize> {
+        self.check_idle()?;
+        Ok(self.escape_times.len())
+    }
+
+    /// The times at which balls escaped through the hole, in order.
+    fn escape_times(&self) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        Ok(self
+            .escape_times
+            .iter()
+            .map(|&t| self.reported_time(t))
+            .collect())
+    }
+
+    /// The coefficient of restitution for collisions with the walls.
+    #[getter(wall_restitution)]
+    fn py_get_wall_restitution(&self) -> PyResult<f64> {
+        self.check_idle()?;
+        Ok(self.wall_restitution())
+    }
+
+    /// Make the walls of the container lossy: every bounce scales the normal
+    /// component of a ball's velocity by `e`, which must be in `(0, 1]`. With
+    /// `e < 1` the gas steadily cools, e.g. for granular-cooling demos.
+    #[setter(wall_restitution)]
+    fn py_set_wall_restitution(&mut self, e: f64) -> PyResult<()> {
+        self.check_idle()?;
+        if !(0. < e && e <= 1.) {
+            return Err(PyValueError::new_err(
+                "The wall restitution must be in (0, 1].",
+            ));
+        }
+        self.set_wall_restitution(e);
+        Ok(())
+    }
+
+    /// Check that the balls are touching before every collision is resolved,
+    /// raising an error if they are not. This catches collisions that fire
+    /// early or late through floating-point drift, at a small cost per step.
+    /// The gap allowed is a thousandth of the distance between the centres at
+    /// contact.
+    #[pyo3(name = "set_strict")]
+    fn py_set_strict(&mut self, strict: bool) -> PyResult<()> {
+        self.check_idle()?;
+        self.set_strict(strict);
+        Ok(())
+    }
+
+    /// Limit the size of the collision queue. Stale events are normally only
+    /// discarded as they come up, so in long, dense runs they accumulate; once
+    /// the queue grows beyond `max_queue_size` events, the stale ones are
+    /// pruned. The limit should comfortably exceed the number of balls, or the
+    /// queue will be pruned on nearly every collision. `None` removes the limit.
+    #[pyo3(name = "set_max_queue_size")]
+    fn py_set_max_queue_size(&mut self, max_queue_size: Option<usize>) -> PyResult<()> {
+        self.check_idle()?;
+        self.set_max_queue_size(max_queue_size);
+        Ok(())
+    }
+
+    /// Set the maximum time the simulation may run to, as a safety valve for
+    /// long runs. Stepping through a collision that would take place after
+    /// this time raises an error instead. Pass `None` to remove the limit.
+    #[pyo3(name = "set_max_time")]
+    fn py_set_max_time(&mut self, max_time: Option<f64>) -> PyResult<()> {
+        self.check_idle()?;
+        self.set_max_time(max_time);
+        Ok(())
+    }
+
+    /// The numeric parameters of the simulation as a dict with the keys
+    /// `"delta"`, `"k_b"`, `"wall_restitution"`, `"time_scale"` and
+    /// `"force_substep"`, and `"max_time"`, `"harmonic_k"` and
+    /// `"max_queue_size"` if they have been set. The force field itself is a
+    /// Python callable rather than a number, so it is left out.
+    fn get_params(&self) -> PyResult<HashMap<String, f64>> {
+        self.check_idle()?;
+        let mut params = HashMap::from([
+            ("delta".to_string(), self.delta()),
+            ("k_b".to_string(), self.k_b()),
+            ("wall_restitution".to_string(), self.wall_restitution()),
+            ("time_scale".to_string(), self.time_scale()),
+            ("force_substep".to_string(), self.force_substep()),
+        ]);
+        if let Some(max_time) = self.max_time() {
+            params.insert("max_time".to_string(), max_time);
+        }
+        if let Some(k) = self.harmonic_k() {
+            params.insert("harmonic_k".to_string(), k);
+        }
+        if let Some(max_queue_size) = self.max_queue_size() {
+            params.insert("max_queue_size".to_string(), max_queue_size as f64);
+        }
+        Ok(params)
+    }
+
+    /// Update any of the parameters returned by `get_params` in one go. Every
+    /// value is checked before any is changed, so a bad value or an unknown key
+    /// leaves the simulation as it was. `"max_queue_size"` must be a whole
+    /// number of events.
+    fn set_params(&mut self, params: HashMap<String, f64>) -> PyResult<()> {
+        self.check_idle()?;
+        for (key, &value) in params.iter() {
+            let valid = match key.as_str() {
+                "delta" => (0. ..1.).contains(&value),
+                "k_b" | "max_time" | "time_scale" | "force_substep" | "harmonic_k" => value > 0.,
+                "max_queue_size" => value >= 1. && value.fract() == 0.,
+                "wall_restitution" => 0. < value && value <= 1.,
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "Unknown parameter `{}`.",
+                        key
+                    )))
+                }
+            };
+            if !valid {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid value {} for parameter `{}`.",
+                    value, key
+                )));
+            }
+        }
+        for (key, value) in params {
+            match key.as_str() {
+                "delta" => self.set_delta(value),
+                "k_b" => self.set_k_b(value),
+                "max_time" => self.set_max_time(Some(value)),
+                "time_scale" => self.set_time_scale(value),
+                "force_substep" => self.set_force_substep(value),
+                "harmonic_k" => self.set_central_harmonic(Some(value)),
+                "max_queue_size" => self.set_max_queue_size(Some(value as usize)),
+                _ => self.set_wall_restitution(value),
+            }
+        }
+        Ok(())
+    }
+
+    /// Run through `no_collisions` collisions on a background thread, so that
+    /// Python is free in the meantime. The returned handle reports progress
+    /// and can cancel the run. Until the handle is joined, or dropped, the
+    /// state of the simulation is held by the run, and any use of this object
+    /// raises a `RuntimeError`.
+    fn run_async(slf: &Bound<'_, Self>, no_collisions: usize) -> PyResult<RunHandle> {
+        let core = {
+            let mut this = slf.borrow_mut();
+            this.check_initialised()?;
+            this.core.take().expect("checked by check_initialised")
+        };
+        Ok(RunHandle::spawn(core, slf.clone().unbind(), no_collisions))
+    }
+
+    /// Run through `n` collisions, usually to thermalise the simulation.
+    #[pyo3(name = "thermalize", signature = (n, verbose=true))]
+    fn py_run_n_collisions(&mut self, n: usize, verbose: bool) -> PyResult<()> {
+        self.check_idle()?;
+        // TODO: rewrite the below as a macro
+        let mut norm;
+        let mut verb;
+        let it: &mut dyn Iterator<Item = usize> = if verbose {
+            println!("Running through collisions...");
+            verb = tqdm(0..n);
+            &mut verb
+        } else {
+            norm = 0..n;
+            &mut norm
+        };
+
+        for _ in it {
+            self.py_next_collision()?;
+        }
+        Ok(())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record the
+    /// indices of the participants of each, in order. Ball-ball collisions are
+    /// recorded as `(i, j)` and collisions with the container or a baffle as
+    /// `(i, None)`. Escapes through the hole are not collisions, so they are
+    /// left out.
+    fn collision_pairs(&mut self, no_collisions: usize) -> PyResult<Vec<(usize, Option<usize>)>> {
+        self.check_idle()?;
+        let mut pairs = Vec::with_capacity(no_collisions);
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if !matches!(event, DataEvent::Escape { .. }) {
+                pairs.push(event.participants());
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// Run the simulation through `no_collisions` collisions and return them
+    /// as a dict of equal-length columns, ready for `pandas.DataFrame`: the
+    /// `time` and `type` of each event as in `next_collision_data`, the indices
+    /// `ball_i` and `ball_j`, where `ball_j` is -1 for the container, and the
+    /// velocity components of both balls before and after, e.g. `pre_vx_i` or
+    /// `post_vy_j`. Velocities that do not exist, such as those of the
+    /// container, are `NaN`.
+    fn run_to_columns(&mut self, no_collisions: usize) -> PyResult<HashMap<String, PyObject>> {
+        self.check_idle()?;
+        const VELOCITY_COLUMNS: [&str; 8] = [
+            "pre_vx_i",
+            "pre_vy_i",
+            "pre_vx_j",
+            "pre_vy_j",
+            "post_vx_i",
+            "post_vy_i",
+            "post_vx_j",
+            "post_vy_j",
+        ];
+        let mut times = Vec::with_capacity(no_collisions);
+        let mut kinds = Vec::with_capacity(no_collisions);
+        let mut ball_i = Vec::with_capacity(no_collisions);
+        let mut ball_j: Vec<i64> = Vec::with_capacity(no_collisions);
+        let mut velocities = vec![Vec::with_capacity(no_collisions); VELOCITY_COLUMNS.len()];
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            let (i, j) = event.participants();
+            times.push(self.reported_time(event.time()));
+            kinds.push(event.kind());
+            ball_i.push(i);
+            ball_j.push(j.map_or(-1, |j| j as i64));
+            let (pres, posts) = event.velocities();
+            let components = |vels: Vec<(f64, f64)>| {
+                (0..2).flat_map(move |k| {
+                    let (vx, vy) = vels.get(k).copied().unwrap_or((f64::NAN, f64::NAN));
+                    [vx, vy]
+                })
+            };
+            let row = components(pres).chain(components(posts));
+            for (column, value) in velocities.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+
+        Ok(Python::with_gil(|py| {
+            let mut columns = vec![
+                (String::from("time"), times.to_object(py)),
+                (String::from("type"), kinds.to_object(py)),
+                (String::from("ball_i"), ball_i.to_object(py)),
+                (String::from("ball_j"), ball_j.to_object(py)),
+            ];
+            for (name, column) in VELOCITY_COLUMNS.into_iter().zip(velocities) {
+                columns.push((String::from(name), column.to_object(py)));
+            }
+            columns.into_iter().collect()
+        }))
+    }
+
+    /// Run the simulation through the next collision and describe it as a dict
+    /// with the `type` of the event (`"ball"`, `"container"` or `"escape"`),
+    /// its `time`, the `indices` of the participants, with `None` for the
+    /// container, and lists of their velocities before and after, `pre_vels`
+    /// and `post_vels`.
+    fn next_collision_data(&mut self) -> PyResult<HashMap<String, PyObject>> {
+        self.check_idle()?;
+        let event = self.step_with_data().map_err(collision_error)?;
+        let (pre_vels, post_vels) = event.velocities();
+        let dict_elements = Python::with_gil(|py| {
+            vec![
+                (String::from("type"), event.kind().to_object(py)),
+                (
+                    String::from("time"),
+                    self.reported_time(event.time()).to_object(py),
+                ),
+                (String::from("indices"), event.participants().to_object(py)),
+                (String::from("pre_vels"), pre_vels.to_object(py)),
+                (String::from("post_vels"), post_vels.to_object(py)),
+            ]
+        });
+        Ok(dict_elements.into_iter().collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record each as
+    /// a dict with the `time` of the collision, the index `i` of the ball, the
+    /// `partner` it collided with as a `PartnerKind`, and the index `j` of the
+    /// partner ball, which is `None` for the container. The events can be
+    /// handed back to `replay`.
+    fn collision_events(
+        &mut self,
+        no_collisions: usize,
+    ) -> PyResult<Vec<HashMap<String, PyObject>>> {
+        self.check_idle()?;
+        let mut events = Vec::with_capacity(no_collisions);
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            let (i, j) = event.participants();
+            let partner = match (&event, j) {
+                (DataEvent::BaffleCollision { .. }, _) => PartnerKind::Baffle,
+                (_, Some(_)) => PartnerKind::Ball,
+                (_, None) => PartnerKind::Container,
+            };
+            let dict_elements = Python::with_gil(|py| {
+                vec![
+                    (String::from("time"), event.time().to_object(py)),
+                    (String::from("i"), i.to_object(py)),
+                    (String::from("j"), j.to_object(py)),
+                    (String::from("partner"), partner.into_py(py)),
+                ]
+            });
+            events.push(dict_elements.into_iter().collect());
+        }
+        Ok(events)
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record the
+    /// absolute time of each, rather than the time since the previous one as
+    /// `collision_times` does. Useful for spectra and event rates.
+    fn event_times(&mut self, no_collisions: usize) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        let mut times = Vec::with_capacity(no_collisions);
+        for _ in 0..no_collisions {
+            self.py_next_collision()?;
+            times.push(self.reported_time(self.global_time));
+        }
+        Ok(times)
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record the
+    /// time and magnitude of the impulse imparted on the container by each
+    /// collision with it, as parallel arrays. Unlike `pressure`, no smoothing
+    /// is applied. The collisions of tracer balls are left out if
+    /// `include_tracers` is false.
+    #[pyo3(signature = (no_collisions, include_tracers=true))]
+    fn wall_impulses(
+        &mut self,
+        no_collisions: usize,
+        include_tracers: bool,
+    ) -> PyResult<(Vec<f64>, Vec<f64>)> {
+        self.check_idle()?;
+        let mut times = Vec::new();
+        let mut impulses = Vec::new();
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let Some(impulse) = event.container_pressure_of(include_tracers) {
+                times.push(self.reported_time(event.time()));
+                impulses.push(impulse);
+            }
+        }
+        Ok((times, impulses))
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record, for
+    /// every collision with the container, the change in the angular momentum
+    /// `L = x vy - y vx` of the ball about the centre of the container. The
+    /// walls are circles about that centre, so the impulse is central and
+    /// these should all vanish up to rounding.
+    fn wall_angular_impulse(&mut self, no_collisions: usize) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        let angular_momentum =
+            |offset: FloatVec, vel: &FloatVec| offset.x * vel.y - offset.y * vel.x;
+        let mut changes = Vec::new();
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::ContainerCollision { pre, post, .. } = event {
+                let offset = self.container.offset(*pre.pos());
+                changes.push(
+                    angular_momentum(offset, post.vel()) - angular_momentum(offset, pre.vel()),
+                );
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Run the simulation through `no_collisions` collisions and resolve the
+    /// pressure on the outer wall by direction: the wall is split into
+    /// `sectors` equal arcs, with sector `k` spanning the angles
+    /// `[2 pi k / sectors, 2 pi (k + 1) / sectors)` anticlockwise from the
+    /// x-axis, and the impulse of every collision with the wall is added to
+    /// the sector it hit. Returns the impulse per unit time per unit arc
+    /// length of each sector. Collisions with the inner wall of an annulus are
+    /// left out, as are those of tracer balls if `include_tracers` is false.
+    #[pyo3(signature = (no_collisions, sectors, include_tracers=true))]
+    fn sectoral_pressure(
+        &mut self,
+        no_collisions: usize,
+        sectors: usize,
+        include_tracers: bool,
+    ) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        if sectors == 0 {
+            return Err(PyValueError::new_err("There must be at least one sector."));
+        }
+        let sector_angle = 2. * std::f64::consts::PI / sectors as f64;
+        let start = self.global_time;
+        let mut impulses = vec![0.; sectors];
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::ContainerCollision { pre, normal, .. } = &event {
+                let offset = self.container.offset(*pre.pos());
+                // The normal of the outer wall points back towards the centre.
+                if offset.dot(normal) > 0. {
+                    continue;
+                }
+                let angle = offset
+                    .y
+                    .atan2(offset.x)
+                    .rem_euclid(2. * std::f64::consts::PI);
+                let sector = ((angle / sector_angle) as usize).min(sectors - 1);
+                impulses[sector] += event.container_pressure_of(include_tracers).unwrap_or(0.);
+            }
+        }
+
+        let elapsed = self.global_time - start;
+        if elapsed <= 0. {
+            return Err(PyValueError::new_err(
+                "No time elapsed over the run, so there is no pressure.",
+            ));
+        }
+        let arc_length = self.container.r * sector_angle;
+        Ok(impulses
+            .into_iter()
+            .map(|impulse| impulse / (elapsed * arc_length))
+            .collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and follow the
+    /// running estimate of the pressure on the walls: the total impulse on
+    /// the container since the start of the run, divided by the time elapsed
+    /// and the length of the walls. The estimate is recorded at `samples`
+    /// evenly spaced collision counts, ending with the last, and returned
+    /// alongside them, so that it can be seen to plateau. The collisions of
+    /// tracer balls are left out if `include_tracers` is false.
+    #[pyo3(signature = (no_collisions, samples, include_tracers=true))]
+    fn pressure_convergence(
+        &mut self,
+        no_collisions: usize,
+        samples: usize,
+        include_tracers: bool,
+    ) -> PyResult<(Vec<usize>, Vec<f64>)> {
+        self.check_idle()?;
+        if samples == 0 || samples > no_collisions {
+            return Err(PyValueError::new_err(
+                "There must be between one sample and one sample per collision.",
+            ));
+        }
+        let counts: Vec<usize> = (1..=samples).map(|k| k * no_collisions / samples).collect();
+        let start = self.global_time;
+        let perimeter = self.container.perimeter();
+        let mut pressures = Vec::with_capacity(samples);
+        let (mut done, mut impulse) = (0, 0.);
+        for &count in counts.iter() {
+            while done < count {
+                let event = self.step_with_data().map_err(collision_error)?;
+                impulse += event.container_pressure_of(include_tracers).unwrap_or(0.);
+                done += 1;
+            }
+            pressures.push(impulse / ((self.global_time - start) * perimeter));
+        }
+        Ok((counts, pressures))
+    }
+
+    /// Run the simulation through `no_collisions` collisions, recording the
+    /// impulse imparted on the container by each (zero for collisions between
+    /// balls), and compute the autocorrelation of that series for lags of up to
+    /// `max_lag` collisions. Returns the lags alongside the autocorrelation,
+    /// normalised so that it is one at lag zero.
+    fn pressure_autocorrelation(
+        &mut self,
+        no_collisions: usize,
+        max_lag: usize,
+    ) -> PyResult<(Vec<usize>, Vec<f64>)> {
+        self.check_idle()?;
+        if max_lag >= no_collisions {
+            return Err(PyValueError::new_err(
+                "The maximum lag must be shorter than the run.",
+            ));
+        }
+        let mut impulses = Vec::with_capacity(no_collisions);
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            impulses.push(event.container_pressure().unwrap_or(0.));
+        }
+
+        let mean = impulses.iter().sum::<f64>() / no_collisions as f64;
+        let deviations: Vec<f64> = impulses.iter().map(|impulse| impulse - mean).collect();
+        let covariance = |lag: usize| -> f64 {
+            deviations
+                .iter()
+                .zip(&deviations[lag..])
+                .map(|(a, b)| a * b)
+                .sum()
+        };
+        let variance = covariance(0);
+        if variance == 0. {
+            return Err(PyValueError::new_err(
+                "The impulse series is constant, so it has no autocorrelation.",
+            ));
+        }
+        let lags: Vec<usize> = (0..=max_lag).collect();
+        let autocorr = lags.iter().map(|&lag| covariance(lag) / variance).collect();
+        Ok((lags, autocorr))
+    }
+
+    /// Estimate the shear viscosity by Green-Kubo: run the simulation through
+    /// `no_collisions` collisions, recording the off-diagonal stress
+    /// `sigma_xy` averaged over fixed windows of time as long as the mean time
+    /// between collisions, and integrate its autocorrelation over lags of up
+    /// to `max_lag` windows, times `area / k_B T`. The stress has a kinetic
+    /// part, `sum v_x v_y / area`, and a collisional part from the impulses.
+    /// Returns the lags alongside the (unnormalised) autocorrelation and the
+    /// estimate. Tracer balls are always included, since the stress is carried
+    /// by the whole fluid.
+    fn shear_viscosity(
+        &mut self,
+        no_collisions: usize,
+        max_lag: usize,
+    ) -> PyResult<(Vec<usize>, Vec<f64>, f64)> {
+        self.check_idle()?;
+        if max_lag >= no_collisions {
+            return Err(PyValueError::new_err(
+                "The maximum lag must be shorter than the run.",
+            ));
+        }
+        let (stresses, window) = self.shear_stress_series(no_collisions, None)?;
+        if stresses.len() <= max_lag {
+            return Err(PyValueError::new_err(
+                "Too few collisions were separated in time to reach the maximum lag.",
+            ));
+        }
+
+        let covariance = |lag: usize| -> f64 {
+            let terms = stresses.len() - lag;
+            stresses
+                .iter()
+                .zip(&stresses[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f64>()
+                / terms as f64
+        };
+        let lags: Vec<usize> = (0..=max_lag).collect();
+        let autocorr: Vec<f64> = lags.iter().map(|&lag| covariance(lag)).collect();
+        // Integrate by the trapezium rule, with the autocorrelation even in the
+        // lag.
+        let integral = window * (autocorr.iter().sum::<f64>() - 0.5 * autocorr[0]);
+        let kt = self.k_b() * self.temperature();
+        let viscosity = self.container.area() * integral / kt;
+        Ok((lags, autocorr, viscosity))
+    }
+
+    /// Run the simulation through `no_collisions` collisions, recording the
+    /// total kinetic energy at `samples + 1` evenly spaced collision counts
+    /// including the start and the end. Returns the collision counts alongside
+    /// the energies. With lossy walls (see `wall_restitution`) the energy
+    /// decays, which is how a granular gas cools. Tracer balls are left out of
+    /// the energy if `include_tracers` is false.
+    #[pyo3(signature = (no_collisions, samples, include_tracers=true))]
+    fn cooling_curve(
+        &mut self,
+        no_collisions: usize,
+        samples: usize,
+        include_tracers: bool,
+    ) -> PyResult<(Vec<usize>, Vec<f64>)> {
+        self.check_idle()?;
+        if samples == 0 || samples > no_collisions {
+            return Err(PyValueError::new_err(
+                "There must be between one sample and one sample per collision.",
+            ));
+        }
+        let counts: Vec<usize> = (0..=samples).map(|k| k * no_collisions / samples).collect();
+        let mut energies = vec![self.kinetic_energy_of(include_tracers)];
+        for window in counts.windows(2) {
+            for _ in window[0]..window[1] {
+                self.py_next_collision()?;
+            }
+            energies.push(self.kinetic_energy_of(include_tracers));
+        }
+        Ok((counts, energies))
+    }
+
+    /// Run the simulation through `no_collisions` collisions, recording at
+    /// `samples` evenly spaced collision counts the fraction of all collisions
+    /// so far that were with the container. Returns the collision counts
+    /// alongside the fractions. The fraction falls as the system grows, since
+    /// collisions between balls come to dominate.
+    fn collision_type_ratio(
+        &mut self,
+        no_collisions: usize,
+        samples: usize,
+    ) -> PyResult<(Vec<usize>, Vec<f64>)> {
+        self.check_idle()?;
+        if samples == 0 || samples > no_collisions {
+            return Err(PyValueError::new_err(
+                "There must be between one sample and one sample per collision.",
+            ));
+        }
+        let counts: Vec<usize> = (1..=samples).map(|k| k * no_collisions / samples).collect();
+        let mut fractions = Vec::with_capacity(samples);
+        let (mut done, mut wall) = (0, 0);
+        for &count in counts.iter() {
+            while done < count {
+                let event = self.step_with_data().map_err(collision_error)?;
+                if matches!(event, DataEvent::ContainerCollision { .. }) {
+                    wall += 1;
+                }
+                done += 1;
+            }
+            fractions.push(wall as f64 / done as f64);
+        }
+        Ok((counts, fractions))
+    }
+
+    /// Run the simulation through `no_collisions` collisions and return the
+    /// speed of each ball averaged over the time elapsed, weighting each speed
+    /// by the length of the free flight it was held for. If no time elapses,
+    /// the current speeds are returned. Balls that escape through the hole are
+    /// dropped, so the speeds are those of the balls that remain, in order.
+    /// Tracer balls are included, and can be picked out by their index.
+    fn time_averaged_speeds(&mut self, no_collisions: usize) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        let start = self.global_time;
+        let mut totals = vec![0.; self.balls.len()];
+        for _ in 0..no_collisions {
+            let speeds: Vec<f64> = self
+                .balls
+                .iter()
+                .map(|ball| ball.vel().magnitude())
+                .collect();
+            let before = self.global_time;
+            let event = self.step_with_data().map_err(collision_error)?;
+            let dt = self.global_time - before;
+            for (total, speed) in totals.iter_mut().zip(speeds) {
+                *total += speed * dt;
+            }
+            if let DataEvent::Escape { index, .. } = event {
+                totals.remove(index);
+            }
+        }
+
+        let elapsed = self.global_time - start;
+        if elapsed <= 0. {
+            return Ok(self
+                .balls
+                .iter()
+                .map(|ball| ball.vel().magnitude())
+                .collect());
+        }
+        Ok(totals.into_iter().map(|total| total / elapsed).collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and measure how
+    /// thoroughly the balls explore the container. The square bounding the
+    /// container is split into a `grid` by `grid` map, and every cell that the
+    /// centre of any ball passes through is marked as visited. Returns the
+    /// fraction of cells whose centres lie inside the container that were
+    /// visited. The paths are sampled at intervals of half a cell, so they are
+    /// assumed to be straight between collisions.
+    fn coverage(&mut self, no_collisions: usize, grid: usize) -> PyResult<f64> {
+        self.check_idle()?;
+        if grid == 0 {
+            return Err(PyValueError::new_err(
+                "The grid must have at least one cell.",
+            ));
+        }
+        let width = 2. * self.container.r / grid as f64;
+        let mut visited = vec![false; grid * grid];
+        let mut mark = |sim: &Simulation, from: FloatVec, to: FloatVec| {
+            let samples = ((to - from).magnitude() / (0.5 * width)).ceil() as usize;
+            for k in 0..=samples {
+                let fraction = if samples == 0 {
+                    0.
+                } else {
+                    k as f64 / samples as f64
+                };
+                visited[sim.grid_cell(from + (to - from) * fraction, grid)] = true;
+            }
+        };
+
+        let mut positions: Vec<FloatVec> = self.balls.iter().map(|ball| *ball.pos()).collect();
+        for _ in 0..no_collisions {
+            self.py_next_collision()?;
+            for (ball, position) in self.balls.iter().zip(positions.iter_mut()) {
+                mark(self, *position, *ball.pos());
+                *position = *ball.pos();
+            }
+        }
+
+        let (mut inside, mut covered) = (0, 0);
+        for (cell, &seen) in visited.iter().enumerate() {
+            if self.grid_cell_inside(cell, grid) {
+                inside += 1;
+                if seen {
+                    covered += 1;
+                }
+            }
+        }
+        Ok(covered as f64 / inside as f64)
+    }
+
+    /// Run the simulation through `no_collisions` collisions and measure the
+    /// fraction of the time that ball centres spend in each cell of a `grid`
+    /// by `grid` map over the square bounding the container. Each free flight
+    /// is sampled at evenly spaced times, so the motion under a force field is
+    /// followed too. The densities are flattened row by row, starting from the
+    /// bottom left, so `x` cell `i` and `y` cell `j` is at `j * grid + i`. Cells
+    /// whose centres lie outside the container are `NaN`, and the rest sum to
+    /// one.
+    fn density_grid(&mut self, no_collisions: usize, grid: usize) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        if grid == 0 {
+            return Err(PyValueError::new_err(
+                "The grid must have at least one cell.",
+            ));
+        }
+        let mut residence = vec![0.; grid * grid];
+        self.sample_run(no_collisions, |sim, dt| {
+            for ball in sim.balls.iter() {
+                residence[sim.grid_cell(*ball.pos(), grid)] += dt;
+            }
+        })?;
+
+        let total: f64 = (0..grid * grid)
+            .filter(|&cell| self.grid_cell_inside(cell, grid))
+            .map(|cell| residence[cell])
+            .sum();
+        Ok(residence
+            .into_iter()
+            .enumerate()
+            .map(|(cell, time)| {
+                if self.grid_cell_inside(cell, grid) {
+                    time / total
+                } else {
+                    f64::NAN
+                }
+            })
+            .collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and measure the
+    /// local temperature in each cell of a `grid` by `grid` map over the square
+    /// bounding the container, laid out as in `density_grid`. The kinetic
+    /// energy of the balls in each cell is averaged over the time they spend
+    /// there, and converted to a temperature by equipartition, as for the
+    /// global `temperature`. Frozen balls are left out, and cells that no ball
+    /// visited or that lie outside the container are `NaN`.
+    fn temperature_grid(&mut self, no_collisions: usize, grid: usize) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        if grid == 0 {
+            return Err(PyValueError::new_err(
+                "The grid must have at least one cell.",
+            ));
+        }
+        let mut energy = vec![0.; grid * grid];
+        let mut residence = vec![0.; grid * grid];
+        self.sample_run(no_collisions, |sim, dt| {
+            for ball in sim.balls.iter().filter(|ball| !ball.frozen) {
+                let cell = sim.grid_cell(*ball.pos(), grid);
+                energy[cell] += 0.5 * ball.vel().dot(ball.vel()) * dt;
+                residence[cell] += dt;
+            }
+        })?;
+
+        let k_b = self.k_b();
+        Ok(energy
+            .into_iter()
+            .zip(residence)
+            .enumerate()
+            .map(|(cell, (energy, time))| {
+                if self.grid_cell_inside(cell, grid) && time > 0. {
+                    energy / (time * k_b)
+                } else {
+                    f64::NAN
+                }
+            })
+            .collect())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and measure the
+    /// local pressure in each cell of a `grid` by `grid` map over the square
+    /// bounding the container, laid out as in `density_grid`. In two
+    /// dimensions `P = (sum m v^2 + sum r_ij . dp_i / t) / (2 A)`: the kinetic
+    /// term is accumulated in the cell each ball is in, weighted by the time
+    /// it spends there, and the virial of each collision between two balls
+    /// goes to the cell holding their point of contact. All balls have unit
+    /// mass. Cells whose centres lie outside the container are `NaN`, and the
+    /// cells cut by the wall read low, since their whole area is counted.
+    fn local_pressure_grid(&mut self, no_collisions: usize, grid: usize) -> PyResult<Vec<f64>> {
+        self.check_idle()?;
+        if grid == 0 {
+            return Err(PyValueError::new_err(
+                "The grid must have at least one cell.",
+            ));
+        }
+        let start = self.global_time;
+        let mut kinetic = vec![0.; grid * grid];
+        let mut virial = vec![0.; grid * grid];
+        self.sample_run_with_events(
+            no_collisions,
+            |sim, dt| {
+                for ball in sim.balls.iter().filter(|ball| !ball.frozen) {
+                    let cell = sim.grid_cell(*ball.pos(), grid);
+                    kinetic[cell] += ball.vel().dot(ball.vel()) * dt;
+                }
+            },
+            |sim, event| {
+                if let DataEvent::BallCollision { pres, posts, .. } = event {
+                    let separation = *pres.0.pos() - *pres.1.pos();
+                    let impulse = *posts.0.vel() - *pres.0.vel();
+                    let contact = *pres.0.pos() - separation.normalize() * pres.0.r;
+                    virial[sim.grid_cell(contact, grid)] += separation.dot(&impulse);
+                }
+            },
+        )?;
+
+        let elapsed = self.global_time - start;
+        if elapsed <= 0. {
+            return Err(PyValueError::new_err(
+                "No time elapsed during the measurement.",
+            ));
+        }
+        let cell_area = (2. * self.container.r / grid as f64).powi(2);
+        Ok(kinetic
+            .into_iter()
+            .zip(virial)
+            .enumerate()
+            .map(|(cell, (kinetic, virial))| {
+                if self.grid_cell_inside(cell, grid) {
+                    (kinetic + virial) / (2. * cell_area * elapsed)
+                } else {
+                    f64::NAN
+                }
+            })
+            .collect())
+    }
+
+    /// Run the simulation and record the pressure exerted on the walls of the
+    /// container by the colliding balls inside it. Return this as a Python
+    /// dictionary. This starts taking data immediately, so if it is run on an
+    /// un-thermalized simulation the results will be janky. `n` is the number
+    /// of collisions that will be recorded, and hence the simulation runtime
+    /// is proportional to `n`. `window_width` gives the width of the window
+    /// used for smooth averaging of the system pressure. Collisions of tracer
+    /// balls with the walls are left out if `include_tracers` is false.
+    #[pyo3(name = "pressure", signature = (n, window_width, include_tracers=true))]
+    fn py_pressure(
+        &mut self,
+        n: usize,
+        window_width: usize,
+        include_tracers: bool,
+    ) -> PyResult<HashMap<String, PyObject>> {
+        self.check_idle()?;
+        self.check_initialised()?;
+        let (mut time_deque, mut pressure_deque): (VecDeque<f64>, VecDeque<f64>) = self
+            .iter_pressure_of(include_tracers)
+            .take(window_width)
+            .collect();
+
+        let time_scale = self.time_scale();
+        let pressure_events = self.iter_pressure_of(include_tracers);
+        let mut pressure_sum: f64 = pressure_deque.iter().sum();
+        let (times, pressures): (Vec<f64>, Vec<f64>) = pressure_events
+            .into_iter()
+            .take(n)
+            .map(|(time, pressure)| {
+                let old_pressure = pressure_deque.pop_front().unwrap();
+                time_deque.pop_front();
+                pressure_sum -= old_pressure;
+                pressure_sum += pressure;
+
+                let t_start = *time_deque.front().unwrap();
+                let t_end = time;
+                time_deque.push_back(time);
+                pressure_deque.push_back(pressure);
+                (time_scale * (t_end - t_start), pressure_sum)
+            })
+            .collect();
+
+        let dict_elements = Python::with_gil(|py| {
+            vec![
+                (String::from("times"), times.to_object(py)),
+                (String::from("pressures"), pressures.to_object(py)),
+            ]
+        });
+
+        let dict_map: HashMap<String, PyObject> = dict_elements.into_iter().collect();
+        Ok(dict_map)
+    }
+
+    /// Run the simulation and record the times at which collisions take place,
+    /// aggregating them into a histogram which is returned as a
+    /// `HistogramResult`. The system must have previously been initialised,
+    /// otherwise a `RuntimeError` is raised. The binning is split between
+    /// `threads` worker threads.
+    #[pyo3(signature = (no_collisions, left, right, bins, threads=1))]
+    fn collision_times(
+        &mut self,
+        no_collisions: usize,
+        left: f64,
+        right: f64,
+        bins: usize,
+        threads: usize,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let (tx_raw, rx_raw) = mpsc::channel();
+        let mut current_time = 0f64;
+
+        println!("Calculating collisions...");
+        for _ in tqdm(0..no_collisions) {
+            self.py_next_collision()?;
+            let collision_delta_t = self.global_time - current_time;
+            current_time = self.global_time;
+            tx_raw.send(self.reported_time(collision_delta_t)).unwrap();
+        }
+        // drop the tx_raw to cause the channel to hang up
+        drop(tx_raw);
+
+        let (tx_hist, rx_hist) = mpsc::channel();
+        thread::spawn(move || {
+            let hist = Histogram::bin_parallel(left, right, bins, rx_raw, threads);
+            tx_hist.send(hist).unwrap();
+        });
+
+        let hist = rx_hist.recv().unwrap();
+        Ok(hist.into())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record, for
+    /// each ball, the time between its successive collisions. Unlike
+    /// `collision_times`, which measures the time between any two events, this
+    /// follows the balls individually; in a dilute gas the free-flight times
+    /// should be exponentially distributed. The pooled values are binned into
+    /// `bins` bins spanning zero to the longest free flight.
+    fn free_flight_times(
+        &mut self,
+        no_collisions: usize,
+        bins: usize,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let mut last_collisions: Vec<Option<f64>> = vec![None; self.balls.len()];
+        let mut flights = Vec::new();
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::Escape { index, .. } = event {
+                // The later balls shift down to fill the gap.
+                last_collisions.remove(index);
+                continue;
+            }
+            let (i, j) = event.participants();
+            for index in std::iter::once(i).chain(j) {
+                if let Some(last) = last_collisions[index] {
+                    flights.push(self.reported_time(event.time() - last));
+                }
+                last_collisions[index] = Some(event.time());
+            }
+        }
+
+        // Nudge the right edge up so that the longest flight lands in the last
+        // bin rather than just outside it.
+        let right = flights.iter().cloned().fold(0f64, f64::max).next_up();
+        Ok(Histogram::bin(0., right, bins, Box::new(flights.into_iter())).into())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and record, for
+    /// each ball, the distance it travels between its successive collisions:
+    /// its speed going into a collision times the time since its last one. The
+    /// pooled values are binned over `[left, right)`. In a dilute gas these
+    /// free paths should be exponentially distributed, with their mean being
+    /// the mean free path.
+    fn free_path_distribution(
+        &mut self,
+        no_collisions: usize,
+        bins: usize,
+        left: f64,
+        right: f64,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let mut last_collisions: Vec<Option<f64>> = vec![None; self.balls.len()];
+        let mut paths = Vec::new();
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::Escape { index, .. } = event {
+                // The later balls shift down to fill the gap.
+                last_collisions.remove(index);
+                continue;
+            }
+            let (i, j) = event.participants();
+            let (pres, _) = event.velocities();
+            for (index, (vx, vy)) in std::iter::once(i).chain(j).zip(pres) {
+                if let Some(last) = last_collisions[index] {
+                    paths.push(vx.hypot(vy) * (event.time() - last));
+                }
+                last_collisions[index] = Some(event.time());
+            }
+        }
+        Ok(Histogram::bin(left, right, bins, Box::new(paths.into_iter())).into())
+    }
+
+    /// Estimate how long each ball will take to reach the wall from the current
+    /// state, treating the balls as if they passed through each other and
+    /// ignoring any force field, and bin the times into `bins` bins spanning
+    /// zero to the longest. The simulation is not advanced. Balls that never
+    /// reach the wall, such as frozen ones, are left out. Returns a dict with
+    /// the per-ball `times`, in the order of the balls that were kept, their
+    /// `counts` and the `edges` of the bins, and the number `excluded`.
+    fn first_wall_times(&self, bins: usize) -> PyResult<HashMap<String, PyObject>> {
+        self.check_idle()?;
+        let times: Vec<f64> = self
+            .balls
+            .iter()
+            .filter_map(|ball| ball.time_to_collision(&self.container))
+            .map(|t| self.reported_time(t))
+            .collect();
+        let excluded = self.balls.len() - times.len();
+
+        // Nudge the right edge up so that the longest time lands in the last
+        // bin rather than just outside it.
+        let right = times.iter().cloned().fold(0f64, f64::max).next_up();
+        let hist: HistogramResult =
+            Histogram::bin(0., right, bins, Box::new(times.clone().into_iter())).into();
+        Ok(Python::with_gil(|py| {
+            [
+                (String::from("times"), times.to_object(py)),
+                (String::from("counts"), hist.counts.to_object(py)),
+                (String::from("edges"), hist.edges.to_object(py)),
+                (String::from("excluded"), excluded.to_object(py)),
+            ]
+            .into_iter()
+            .collect()
+        }))
+    }
+
+    /// Run the simulation through `no_collisions` collisions and bin the
+    /// relative speeds, `|v_i - v_j|`, of the pairs of balls at the moment they
+    /// collide. Faster pairs collide more often, so this is biased towards
+    /// higher speeds than the relative speeds of the bulk. Collisions with the
+    /// container are skipped.
+    fn collision_relative_speeds(
+        &mut self,
+        no_collisions: usize,
+        bins: usize,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let mut speeds = Vec::new();
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::BallCollision { pres, .. } = event {
+                speeds.push((*pres.0.vel() - *pres.1.vel()).magnitude());
+            }
+        }
+
+        // Nudge the right edge up so that the fastest pair lands in the last
+        // bin rather than just outside it.
+        let right = speeds.iter().cloned().fold(0f64, f64::max).next_up();
+        Ok(Histogram::bin(0., right, bins, Box::new(speeds.into_iter())).into())
+    }
+
+    /// Run the simulation through `no_collisions` collisions and bin the
+    /// distance between the centres of the balls at every collision between
+    /// two of them, over `[left, right)`. This should be sharply peaked at the
+    /// sum of the radii, which is a check that collisions fire at contact.
+    fn contact_distance_distribution(
+        &mut self,
+        no_collisions: usize,
+        bins: usize,
+        left: f64,
+        right: f64,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let mut distances = Vec::new();
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::BallCollision { pres, .. } = event {
+                distances.push((*pres.0.pos() - *pres.1.pos()).magnitude());
+            }
+        }
+        Ok(Histogram::bin(left, right, bins, Box::new(distances.into_iter())).into())
+    }
+
+    /// Bin the kinetic energies, `0.5 * m * v^2`, of the balls in their current
+    /// state into a histogram. All balls have unit mass. This is a snapshot, so
+    /// the simulation is not advanced. Tracer balls are left out if
+    /// `include_tracers` is false.
+    #[pyo3(signature = (left, right, bins, include_tracers=true))]
+    fn energy_distribution(
+        &self,
+        left: f64,
+        right: f64,
+        bins: usize,
+        include_tracers: bool,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let energies: Vec<f64> = self
+            .observed_balls(include_tracers)
+            .map(|ball| 0.5 * ball.vel().dot(ball.vel()))
+            .collect();
+        Ok(Histogram::bin(left, right, bins, Box::new(energies.into_iter())).into())
+    }
+
+    /// Bin the current velocities of the balls into a `bins` by `bins` grid
+    /// over `[-v_max, v_max)` in both `vx` and `vy`. The counts are returned
+    /// flattened row by row, so that `vx` bin `i` and `vy` bin `j` is at
+    /// `i * bins + j`, alongside the edges of the bins, which are the same on
+    /// both axes. Tracer balls are left out if `include_tracers` is false.
+    #[pyo3(signature = (bins, v_max, include_tracers=true))]
+    fn velocity_histogram(
+        &self,
+        bins: usize,
+        v_max: f64,
+        include_tracers: bool,
+    ) -> PyResult<(Vec<usize>, Vec<f64>)> {
+        self.check_idle()?;
+        let velocities = self
+            .observed_balls(include_tracers)
+            .map(|ball| (ball.vel().x, ball.vel().y));
+        let counts = Histogram::bin_2d(-v_max, v_max, bins, velocities);
+        Ok((counts, Histogram::empty(-v_max, v_max, bins).edges()))
+    }
+
+    /// The coarse-grained entropy, `S = -sum p ln p`, of the current velocities
+    /// of the balls, binned as in `velocity_histogram`. The probabilities are
+    /// the fractions of the balls on the grid that fall in each cell, and empty
+    /// cells contribute nothing. Over a run this should rise as the H-function
+    /// falls. An empty grid has zero entropy. Tracer balls are left out if
+    /// `include_tracers` is false.
+    #[pyo3(signature = (bins, v_max, include_tracers=true))]
+    fn velocity_entropy(&self, bins: usize, v_max: f64, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        let (counts, _) = self.velocity_histogram(bins, v_max, include_tracers)?;
+        let total: usize = counts.iter().sum();
+        Ok(counts
+            .into_iter()
+            .filter(|&count| count > 0)
+            .map(|count| {
+                let p = count as f64 / total as f64;
+                -p * p.ln()
+            })
+            .sum())
+    }
+
+    /// Measure the compressibility factor, `Z = P A / (N k_B T)`, over the
+    /// next `no_collisions` collisions. The pressure is found from the virial
+    /// theorem: on top of the ideal part, every collision between two balls
+    /// contributes `r_ij . dp_i / (2 A t)`, where `r_ij` separates their centres
+    /// and `dp_i` is the impulse on one of them. For hard disks `Z` tends to one
+    /// in the dilute limit and exceeds one at finite packing. Tracer balls are
+    /// always included, since `Z` describes the whole fluid they collide in.
+    fn compressibility_factor(&mut self, no_collisions: usize) -> PyResult<f64> {
+        self.check_idle()?;
+        let ideal = self.kinetic_energy(); // N k_B T in two dimensions
+        if ideal == 0. {
+            return Err(PyValueError::new_err(
+                "The compressibility factor needs moving balls.",
+            ));
+        }
+        let start = self.global_time;
+        let mut virial = 0.;
+        for _ in 0..no_collisions {
+            let event = self.step_with_data().map_err(collision_error)?;
+            if let DataEvent::BallCollision { pres, posts, .. } = event {
+                let separation = *pres.0.pos() - *pres.1.pos();
+                let impulse = *posts.0.vel() - *pres.0.vel();
+                virial += separation.dot(&impulse);
+            }
+        }
+        let elapsed = self.global_time - start;
+        if elapsed <= 0. {
+            return Err(PyValueError::new_err(
+                "No time elapsed during the measurement.",
+            ));
+        }
+        // The area cancels between the pressure and `Z`.
+        Ok(1. + virial / (2. * elapsed * ideal))
+    }
+
+    /// Estimate the second virial coefficient, `B2`, from the compressibility
+    /// factor measured over the next `no_collisions` collisions, by fitting
+    /// `Z = 1 + B2 rho` at the number density `rho = N / A` of this simulation.
+    /// With only a single density, the higher virial terms cannot be separated
+    /// out, so the estimate is only good in the dilute limit. For hard disks of
+    /// radius `r` the exact value is `2 pi r^2`.
+    fn second_virial(&mut self, no_collisions: usize) -> PyResult<f64> {
+        self.check_idle()?;
+        let density = self.balls.len() as f64 / self.container.area();
+        let z = self.compressibility_factor(no_collisions)?;
+        Ok((z - 1.) / density)
+    }
+
+    /// Calculate Boltzmann's H-function, `H = sum f ln f * width`, for the
+    /// current speed distribution of the balls, binned into `bins` bins over
+    /// `[0, v_max)` and normalised to a density `f`. Empty bins contribute
+    /// nothing. Calling this repeatedly over a run shows `H` decreasing towards
+    /// its equilibrium value. Tracer balls are left out if `include_tracers`
+    /// is false.
+    #[pyo3(signature = (bins, v_max, include_tracers=true))]
+    fn h_function(&self, bins: usize, v_max: f64, include_tracers: bool) -> PyResult<f64> {
+        self.check_idle()?;
+        let speeds: Vec<f64> = self
+            .observed_balls(include_tracers)
+            .map(|ball| ball.vel().magnitude())
+            .collect();
+        let hist: HistogramResult =
+            Histogram::bin(0., v_max, bins, Box::new(speeds.into_iter())).into();
+        Ok(hist
+            .density()
+            .into_iter()
+            .filter(|&f| f > 0.)
+            .map(|f| f * f.ln() * hist.width)
+            .sum())
+    }
+
+    /// Heuristically decide whether the simulation has reached equilibrium.
+    /// This runs the simulation through `window` collisions, so it advances
+    /// the state. Because the kinetic energy is conserved by the elastic
+    /// collisions, the H-function of the speed distribution is compared at the
+    /// start and end of the window instead; the simulation is considered
+    /// thermalized if it changed by less than `tolerance`.
+    fn is_thermalized(&mut self, window: usize, tolerance: f64) -> PyResult<bool> {
+        self.check_idle()?;
+        let n = self.balls.len();
+        if n == 0 {
+            return Ok(true);
+        }
+        let bins = (n as f64).sqrt().ceil() as usize;
+        let rms_speed = (2. * self.kinetic_energy() / n as f64).sqrt();
+        let v_max = 3. * rms_speed;
+
+        let h_start = self.h_function(bins, v_max, true)?;
+        for _ in 0..window {
+            self.py_next_collision()?;
+        }
+        let h_end = self.h_function(bins, v_max, true)?;
+
+        Ok((h_end - h_start).abs() < tolerance)
+    }
+
+    /// Run the simulation and track the positions of the balls. Panic in the
+    /// secondary thread when a ball ends up outside the container and give the
+    /// collision number and the global time at which it happened.
+    fn track_positions(&mut self, no_collisions: usize) -> PyResult<()> {
+        self.check_idle()?;
+        let (tx, rx) = mpsc::channel();
+
+        println!("Calculating collisions...");
+        for i in tqdm(0..no_collisions) {
+            self.py_next_collision()?;
+            let r_squareds: Vec<f64> = self
+                .balls
+                .iter()
+                .map(|ball| ball.pos().dot(ball.pos()))
+                .collect();
+            let global_time = self.global_time;
+            tx.send((i, global_time, r_squareds)).unwrap();
+        }
+        // drop the tx_raw to cause the channel to hang up
+        drop(tx);
+
+        let checker = thread::spawn(move || {
+            for info in rx {
+                let (i, global_time, r_squareds) = info;
+                for r_squared in r_squareds {
+                    if r_squared > 1f64 {
+                        panic!("Collision: {};\tGlobal time: {}", i, global_time);
+                    }
+                }
+            }
+        });
+
+        checker.join().unwrap();
+
+        Ok(())
+    }
+
+    /// Run the simulation and record the times between which `n` collisions
+    /// take place. Aggregate the data into a histogram, returned as a
+    /// `HistogramResult`. The system must have previously been initialised,
+    /// otherwise a `RuntimeError` is raised. The binning is split between
+    /// `threads` worker threads.
+    #[pyo3(signature = (n, no_collisions, left, right, bins, threads=1))]
+    fn nth_collision_times(
+        &mut self,
+        n: usize,
+        no_collisions: usize,
+        left: f64,
+        right: f64,
+        bins: usize,
+        threads: usize,
+    ) -> PyResult<HistogramResult> {
+        self.check_idle()?;
+        let (tx_raw, rx_raw) = mpsc::channel();
+        let mut current_time = 0f64;
+
+        println!("Calculating collisions...");
+        for _ in tqdm(0..no_collisions / n) {
+            let mut local_sum = 0f64;
+            for _ in 0..n {
+                self.py_next_collision()?;
+                let collision_delta_t = self.global_time - current_time;
+                current_time = self.global_time;
+                local_sum += collision_delta_t;
+            }
+            tx_raw.send(self.reported_time(local_sum)).unwrap();
+        }
+        // drop the tx_raw to cause the channel to hang up
+        drop(tx_raw);
+
+        let (tx_hist, rx_hist) = mpsc::channel();
+        thread::spawn(move || {
+            let hist = Histogram::bin_parallel(left, right, bins, rx_raw, threads);
+            tx_hist.send(hist).unwrap();
+        });
+
+        let hist = rx_hist.recv().unwrap();
+        Ok(hist.into())
+    }
+}
+
+// fn run_later(n: usize, verbose: bool) -> impl Iterator<Item = usize> {
+//     let mut progress = None;
+//     let mut regular = None;
+
+//     if verbose {
+//         progress = Some(tqdm(0..n));
+//     } else {
+//         regular = Some(0..n)
+//     }
+
+//     progress
+//         .into_iter()
+//         .flatten()
+//         .chain(regular.into_iter().flatten())
+// }
+
+impl Simulation {
+    fn grid_cell(&self, pos: FloatVec, grid: usize) -> usize {
+        // The cell of a `grid` by `grid` map over the square bounding the
+        // container that `pos` falls in, counting row by row from the bottom
+        // left. Positions outside the square are put in the nearest cell.
+        let outer = self.container.r;
+        let width = 2. * outer / grid as f64;
+        let offset = self.container.offset(pos);
+        let index = |x: f64| ((x + outer) / width).floor().clamp(0., (grid - 1) as f64) as usize;
+        index(offset.y) * grid + index(offset.x)
+    }
+
+    fn grid_cell_inside(&self, cell: usize, grid: usize) -> bool {
+        // Whether the centre of `cell`, numbered as in `grid_cell`, lies inside
+        // the container.
+        let outer = self.container.r;
+        let inner = self.container.inner_radius.unwrap_or(0.);
+        let width = 2. * outer / grid as f64;
+        let centre = |index: usize| -outer + (index as f64 + 0.5) * width;
+        let distance = FloatVec::new(centre(cell % grid), centre(cell / grid)).magnitude();
+        inner <= distance && distance <= outer
+    }
+
+    fn sample_run(
+        &mut self,
+        no_collisions: usize,
+        sample: impl FnMut(&Simulation, f64),
+    ) -> PyResult<()> {
+        // Run the simulation through `no_collisions` collisions, handing the
+        // state to `sample` at evenly spaced times during every free flight,
+        // alongside the time each sample stands for. The samples are taken by
+        // stepping, so the motion under a force field is followed too.
+        self.sample_run_with_events(no_collisions, sample, |_, _| ())
+    }
+
+    fn sample_run_with_events(
+        &mut self,
+        no_collisions: usize,
+        mut sample: impl FnMut(&Simulation, f64),
+        mut on_event: impl FnMut(&Simulation, DataEvent),
+    ) -> PyResult<()> {
+        // As `sample_run`, but also hand every collision that ends a free
+        // flight to `on_event`, alongside the state straight after it.
+        const SAMPLES: usize = 16;
+        let target = self.collision_count + no_collisions;
+        while self.collision_count < target {
+            let start = self.global_time;
+            let dt =
+                (self.next_collision_time().map_err(collision_error)? - start) / SAMPLES as f64;
+            for k in 0..SAMPLES {
+                self.run_until_time(start + (k as f64 + 0.5) * dt)
+                    .map_err(collision_error)?;
+                sample(self, dt);
+                if self.collision_count >= target {
+                    break;
+                }
+            }
+            if self.collision_count < target {
+                let event = self.step_with_data().map_err(collision_error)?;
+                on_event(self, event);
+            }
+        }
+        Ok(())
+    }
+
+    fn shear_stress_series(
+        &mut self,
+        no_collisions: usize,
+        window: Option<f64>,
+    ) -> PyResult<(Vec<f64>, f64)> {
+        // Run the simulation through `no_collisions` collisions, and record
+        // the off-diagonal stress averaged over consecutive windows of time of
+        // length `window`, which defaults to the mean time between collisions.
+        // The stress is integrated exactly, as the kinetic part is constant
+        // between collisions and each collision adds its impulse, and the
+        // averages are differences of the integral across each window, so an
+        // impulse is never spread over a short gap. The last, partial window is
+        // dropped. Returns the averages alongside the window.
+        let origin = self.container.centre;
+        let area = self.container.area();
+        let kinetic = |sim: &Simulation| -> f64 {
+            sim.balls
+                .iter()
+                .map(|ball| ball.vel().x * ball.vel().y)
+                .sum()
+        };
+
+        // The integrated stress, times the area, as `(t, integral, slope)` at
+        // the start and after every collision, growing at `slope` until the
+        // next.
+        let start = self.global_time;
+        let mut integral = 0.;
+        let mut path = vec![(start, integral, kinetic(self))];
+        for _ in 0..no_collisions {
+            let (t, _, slope) = path[path.len() - 1];
+            let event = self.step_with_data().map_err(collision_error)?;
+            integral += slope * (event.time() - t) + event.shear_virial(&origin);
+            path.push((event.time(), integral, kinetic(self)));
+        }
+
+        let elapsed = self.global_time - start;
+        let window = window.unwrap_or(elapsed / no_collisions as f64);
+        if window.is_nan() || window <= 0. {
+            return Err(PyValueError::new_err(
+                "The stress must be averaged over a positive window of time.",
+            ));
+        }
+        let mut segment = 0;
+        let mut integral_at = |time: f64| {
+            while segment + 1 < path.len() && path[segment + 1].0 <= time {
+                segment += 1;
+            }
+            let (t, integral, slope) = path[segment];
+            integral + slope * (time - t)
+        };
+        let no_windows = (elapsed / window).floor() as usize;
+        let mut before = integral_at(start);
+        let stresses = (1..=no_windows)
+            .map(|k| {
+                let after = integral_at(start + k as f64 * window);
+                let stress = (after - before) / (area * window);
+                before = after;
+                stress
+            })
+            .collect();
+        Ok((stresses, window))
+    }
+
+    fn check_initialised(&self) -> PyResult<()> {
+        // Running the simulation checks this itself, see `ensure_initialised`,
+        // so a new method that steps the simulation is covered without doing
+        // anything. It only needs checking up front where nothing is run, as
+        // in `ball_next_collision`, where a failure part way through could not
+        // be reported, as in `iter_pressure`, or where it would be reported
+        // late, as by a background run.
+        self.check_idle()?;
+        self.ensure_initialised().map_err(collision_error)
+    }
+
+    fn check_idle(&self) -> PyResult<()> {
+        // The state is away while a run started by `run_async` is going, and
+        // nothing else can be done with the simulation until it is joined.
+        if self.core.is_some() {
+            Ok(())
+        } else {
+            Err(PyRuntimeError::new_err(
+                "The simulation is running in the background; join its RunHandle first.",
+            ))
+        }
+    }
+}
+
+pub(crate) fn collision_error(e: DynamicsError) -> PyErr {
+    // Describe a failure to run through a collision to Python.
+    match e {
+        DynamicsError::TimeLimitExceeded => {
+            PyRuntimeError::new_err("The simulation reached its maximum time.")
+        }
+        DynamicsError::NonFiniteState { collision, time } => PyValueError::new_err(format!(
+            "Non-finite ball state after collision {} at time {}.",
+            collision, time
+        )),
+        DynamicsError::MistimedCollision { collision, gap } => PyValueError::new_err(format!(
+            "Collision {} was resolved with a gap of {} between the balls.",
+            collision, gap
+        )),
+        DynamicsError::Uninitialised => PyRuntimeError::new_err("call initialise() first"),
+        _ => PyValueError::new_err("Bad dynamics in the simulation."),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dynamics::maths::approx_eq_f64;
+
+    fn glancing() -> Simulation {
+        // Two balls on a deterministic, off-axis trajectory towards each other
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.1).into(), (-1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        sim
+    }
+
+    #[test]
+    fn collision_pairs() {
+        let mut sim = glancing();
+        let pairs = sim.collision_pairs(4).unwrap();
+
+        assert_eq!(
+            pairs,
+            vec![(0, Some(1)), (1, None), (0, None), (0, Some(1))]
+        );
+    }
+
+    #[test]
+    fn max_time() {
+        let mut sim = glancing();
+        sim.set_max_time(Some(2.));
+        let result = sim.run_collisions(1_000);
+
+        assert!(matches!(result, Err(DynamicsError::TimeLimitExceeded)));
+        assert!(sim.global_time <= 2.);
+    }
+
+    #[test]
+    fn run_until_time() {
+        // A single ball bouncing across a diameter hits the wall at 0.9, 2.7
+        // and 4.5.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let (container_collisions, elapsed) = sim.py_run_until_time(5.).unwrap();
+
+        assert_eq!(container_collisions, 3);
+        assert!(approx_eq_f64(elapsed, 5., 1));
+    }
+
+    #[test]
+    fn reverse_velocities() {
+        let mut sim = glancing();
+        let start = sim.get_balls().unwrap();
+        sim.run_collisions(10).unwrap();
+        let elapsed = sim.global_time;
+        sim.reverse_velocities().unwrap();
+        sim.initialise().unwrap();
+        sim.run_until_time(2. * elapsed).unwrap();
+
+        for (ball, original) in sim.balls.iter().zip(start.iter()) {
+            let offset = *ball.pos() - *original.pos();
+            assert!(offset.magnitude() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn overlapping_pairs() {
+        let mut sim = Simulation::new(1.);
+        for pos in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (0., 0.), (0.15, 0.)] {
+            sim.balls.push(Ball::new(pos.into(), (0., 0.).into(), 0.1));
+        }
+
+        assert_eq!(sim.overlapping_pairs(1e-3).unwrap(), vec![(3, 4)]);
+        assert!(sim.overlapping_pairs(0.1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ideal_pressure() {
+        let mut sim = glancing();
+        sim.run_collisions(50).unwrap();
+        let energy: f64 = sim
+            .balls
+            .iter()
+            .map(|ball| 0.5 * ball.vel().dot(ball.vel()))
+            .sum();
+        let expected = energy / std::f64::consts::PI;
+
+        assert!((sim.ideal_pressure() - expected).abs() < 1e-12);
+        assert_eq!(Simulation::new(1.).ideal_pressure(), 0.);
+    }
+
+    #[test]
+    fn add_ball() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::new(1.);
+        let indices: Vec<usize> = Python::with_gil(|py| {
+            (0..3)
+                .map(|_| Py::new(py, Ball::default()).unwrap())
+                .map(|ball| sim.add_ball(ball).unwrap())
+                .collect()
+        });
+
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn harmonic_force_field() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0.3, 0.).into(), (0., 0.6).into(), 0.05));
+        Python::with_gil(|py| {
+            let force = py.eval_bound("lambda x, y: (-4. * x, -4. * y)", None, None);
+            sim.py_set_force_field(Some(force.unwrap().unbind()), 1e-3)
+                .unwrap();
+        });
+        sim.initialise().unwrap();
+        sim.set_max_time(Some(5.));
+        let result = sim.step_through_collision();
+
+        // The ball is on a circular orbit of radius 0.3, so it should never
+        // reach the wall.
+        assert!(matches!(result, Err(DynamicsError::TimeLimitExceeded)));
+        let r = sim.balls[0].pos().magnitude();
+        assert!((r - 0.3).abs() < 0.01);
+    }
+
+    #[test]
+    fn non_finite_state() {
+        let corrupted = || {
+            let mut sim = glancing();
+            sim.run_collisions(3).unwrap();
+            sim.balls[0].pos = (f64::NAN, 0.).into();
+            sim
+        };
+        let result = corrupted().run_collisions(10);
+        assert!(matches!(
+            result,
+            Err(DynamicsError::NonFiniteState { collision: 3, .. })
+        ));
+
+        pyo3::prepare_freethreaded_python();
+        let mut sim = corrupted();
+        let message = (0..10)
+            .find_map(|_| sim.py_next_collision().err())
+            .unwrap()
+            .to_string();
+        assert!(message.contains("collision 3"));
+    }
+
+    #[test]
+    fn energy_distribution() {
+        let mut sim = Simulation::new(1.);
+        for (i, speed) in [0.5, 1., 1.5, 1.5].into_iter().enumerate() {
+            let pos = (-0.6 + 0.4 * i as f64, 0.);
+            sim.balls
+                .push(Ball::new(pos.into(), (0., speed).into(), 0.1));
+        }
+        let hist = sim.energy_distribution(0., 1.5, 3, true).unwrap();
+
+        // energies of 0.125, 0.5, 1.125 and 1.125
+        assert_eq!(hist.counts, vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn set_balls() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = glancing();
+        Python::with_gil(|py| {
+            let balls = [(-0.5, 0.), (0., 0.), (0.5, 0.)]
+                .into_iter()
+                .map(|pos| Ball::new(pos.into(), (0., 1.).into(), 0.1))
+                .map(|ball| Py::new(py, ball).unwrap())
+                .collect();
+            sim.set_balls(balls).unwrap();
+        });
+
+        assert_eq!(sim.ball_count().unwrap(), 3);
+        assert!(sim.collisions.is_empty());
+
+        // Nothing recorded about the old balls carries over, and a smaller set
+        // still saves and loads.
+        sim.initialise().unwrap();
+        sim.run_collisions(5).unwrap();
+        assert!(sim.save_state().3.iter().any(|&count| count > 0));
+        Python::with_gil(|py| {
+            let balls = [(-0.5, 0.), (0.5, 0.)]
+                .into_iter()
+                .map(|pos| Ball::new(pos.into(), (1., 0.).into(), 0.1))
+                .map(|ball| Py::new(py, ball).unwrap())
+                .collect();
+            sim.set_balls(balls).unwrap();
+        });
+        let state = sim.save_state();
+        assert!(state.3.is_empty());
+        assert_eq!(sim.simultaneous_collisions, 0);
+        sim.load_state(state).unwrap();
+        assert_eq!(sim.ball_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn to_com_frame() {
+        let mut sim = glancing();
+        sim.balls[1].set_vel((0.5, 2.).into());
+        sim.py_to_com_frame().unwrap();
+
+        assert!(sim.com_velocity().magnitude() < 1e-15);
+        assert_eq!(sim.com_position().unwrap(), (0., 0.05));
+    }
+
+    #[test]
+    fn run_until_wall() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.5).into(), (0., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+
+        assert_eq!(sim.py_run_until_wall().unwrap(), 1);
+        assert!(approx_eq_f64(sim.global_time, 0.9, 1));
+    }
+
+    #[test]
+    fn k_b() {
+        let mut sim = glancing();
+        let temperature = sim.temperature();
+        sim.py_set_k_b(2.).unwrap();
+
+        assert_eq!(sim.py_get_k_b().unwrap(), 2.);
+        assert!(approx_eq_f64(sim.temperature(), 0.5 * temperature, 1));
+        assert!(sim.py_set_k_b(0.).is_err());
+    }
+
+    #[test]
+    fn h_function() {
+        let with_speeds = |speeds: Vec<f64>| {
+            let mut sim = Simulation::new(1.);
+            for speed in speeds {
+                sim.balls
+                    .push(Ball::new((0., 0.).into(), (speed, 0.).into(), 0.01));
+            }
+            sim
+        };
+        let delta = with_speeds(vec![1.; 10]);
+        let broad = with_speeds((0..10).map(|i| 0.1 + 0.2 * i as f64).collect());
+
+        assert!(delta.h_function(10, 2., true).unwrap() > broad.h_function(10, 2., true).unwrap());
+    }
+
+    #[test]
+    fn thermalized() {
+        let mut sim = Simulation::thermalized(1., 50, 0.02, 2.5, 7).unwrap();
+
+        assert_eq!(sim.ball_count().unwrap(), 50);
+        assert!(sim.overlapping_pairs(0.).unwrap().is_empty());
+        assert!((sim.temperature() - 2.5).abs() < 1e-9);
+        assert!(sim.com_velocity().magnitude() < 1e-9);
+        sim.run_collisions(500).unwrap();
+        assert!((sim.temperature() - 2.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn is_thermalized() {
+        let mut sim = Simulation::thermalized(1., 100, 0.02, 1., 3).unwrap();
+        for ball in sim.balls.iter_mut() {
+            // give every ball the same speed, keeping its direction
+            ball.set_vel(ball.vel().normalize() * 1.4);
+        }
+        sim.initialise().unwrap();
+        assert!(!sim.is_thermalized(1_000, 0.5).unwrap());
+
+        sim.run_collisions(5_000).unwrap();
+        assert!(sim.is_thermalized(1_000, 0.5).unwrap());
+    }
+
+    #[test]
+    fn pair_cache() {
+        // Without the cache, each ball-ball collision would require 18 pair
+        // predictions for 10 balls, as the colliding pair is predicted from
+        // both sides. Container collisions require 9.
+        let mut sim = Simulation::thermalized(1., 10, 0.05, 1., 11).unwrap();
+        assert_eq!(sim.pair_calculations, 45);
+        let (mut ball_collisions, mut container_collisions) = (0, 0);
+        for _ in 0..200 {
+            match sim.step_with_data().unwrap().participants() {
+                (_, Some(_)) => ball_collisions += 1,
+                (_, None) => container_collisions += 1,
+            }
+        }
+
+        assert!(ball_collisions > 0);
+        assert_eq!(
+            sim.pair_calculations,
+            45 + 17 * ball_collisions + 9 * container_collisions
+        );
+        // The cache is only kept within a step, so it never grows.
+        assert!(sim.pair_cache.is_empty());
+    }
+
+    #[test]
+    fn fill_lattice() {
+        let mut sim = Simulation::new(1.);
+        let placed = sim.fill_lattice(3, 3, 0.2, 0.05, "square").unwrap();
+
+        assert_eq!(placed, 9);
+        let mut positions: Vec<(f64, f64)> = sim
+            .balls
+            .iter()
+            .map(|ball| (ball.pos().x, ball.pos().y))
+            .collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let coords = [-0.2, 0., 0.2];
+        for (k, (x, y)) in positions.into_iter().enumerate() {
+            assert!((x - coords[k / 3]).abs() < 1e-12);
+            assert!((y - coords[k % 3]).abs() < 1e-12);
+        }
+        assert!(sim.fill_lattice(3, 3, 0.2, 0.05, "hexagonal").is_err());
+
+        // In an annulus the middle site falls in the hole.
+        let mut annulus = Simulation::py_new(1., Some(0.1)).unwrap();
+        assert_eq!(annulus.fill_lattice(3, 3, 0.2, 0.05, "square").unwrap(), 8);
+        assert!(annulus
+            .balls
+            .iter()
+            .all(|ball| ball.pos().magnitude() > 0.15));
+        assert!(!annulus
+            .balls
+            .iter()
+            .any(|ball| annulus.outside_container(ball)));
+    }
+
+    #[test]
+    fn collision_diameter() {
+        let mut sim = Simulation::new(1.);
+        for (pos, r) in [((-0.5, 0.), 0.1), ((0., 0.), 0.2), ((0.5, 0.), 0.3)] {
+            sim.balls.push(Ball::new(pos.into(), (0., 0.).into(), r));
+        }
+
+        assert!(approx_eq_f64(sim.collision_diameter(0, 2).unwrap(), 0.4, 1));
+        assert!(sim.collision_diameter(0, 3).is_err());
+        // (0.3 + 0.4 + 0.5) / 3
+        assert!((sim.mean_collision_diameter().unwrap() - 0.4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn wall_impulses() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let (times, impulses) = sim.wall_impulses(4, true).unwrap();
+
+        assert_eq!(times.len(), 4);
+        for pair in times.windows(2) {
+            assert!((pair[1] - pair[0] - 1.8).abs() < 1e-5);
+        }
+        for impulse in impulses {
+            assert!((impulse - 2.).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn central_harmonic() {
+        // With `k = 4` the orbit is the ellipse `(0.3 cos 2t, 0.15 sin 2t)`.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0.3, 0.).into(), (0., 0.3).into(), 0.05));
+        sim.py_set_central_harmonic(Some(4.)).unwrap();
+        sim.initialise().unwrap();
+        let pi = std::f64::consts::PI;
+        let expected = [(0., 0.15), (-0.3, 0.), (0., -0.15), (0.3, 0.)];
+        for (quarter, (x, y)) in expected.into_iter().enumerate() {
+            sim.run_until_time((quarter + 1) as f64 * pi / 4.).unwrap();
+            let offset = *sim.balls[0].pos() - (x, y).into();
+            assert!(offset.magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn central_harmonic_many_balls() {
+        // Every ball's orbit bends between collisions, so the predictions for
+        // the bystanders of each collision must be renewed as well. Collisions
+        // are predicted along straight lines over the last sub-step, so pairs
+        // may touch slightly past contact, but never by more than that.
+        pyo3::prepare_freethreaded_python();
+        for k in [5., 20., 50.] {
+            let mut sim = Simulation::thermalized(1., 80, 0.04, 1., 23).unwrap();
+            sim.set_central_harmonic(Some(k));
+            assert!(sim.detect_tunneling(1000, 1e-5).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn free_flight_times() {
+        // A single ball on a chord a distance 0.5 from the centre stays on
+        // chords of the same length, `2 * sqrt(0.9^2 - 0.5^2)`, after every
+        // bounce.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.5).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let hist = sim.free_flight_times(10, 4).unwrap();
+
+        let chord = 2. * (0.81f64 - 0.25).sqrt();
+        assert_eq!(hist.counts, vec![0, 0, 0, 9]);
+        assert!((hist.edges[4] - chord).abs() < 1e-5);
+
+        // Two balls colliding head-on at the centre bounce back to the wall,
+        // each taking `0.9 - 0.1` to get there. The first flight of the
+        // second ball starts at a collision in which it is the partner, so
+        // both balls of a collision must be followed to find it.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.).into(), (-1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let hist = sim.free_flight_times(3, 4).unwrap();
+
+        assert_eq!(hist.counts, vec![0, 0, 0, 2]);
+        assert!((hist.edges[4] - 0.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn event_ordering() {
+        use event::{CollisionEvent, CollisionPartner, EVENT_TIME_ULP};
+        let event_at =
+            |t: f64| CollisionEvent::new(0, CollisionPartner::Container, t, Default::default());
+        let t = 1.5f64;
+        let shifted = f64::from_bits(t.to_bits() + EVENT_TIME_ULP + 1);
+
+        assert!(event_at(t) < event_at(shifted));
+        assert!(!event_at(t).is_simultaneous(&event_at(shifted)));
+        let close = f64::from_bits(t.to_bits() + EVENT_TIME_ULP);
+        assert!(event_at(t) < event_at(close));
+        assert!(event_at(t).is_simultaneous(&event_at(close)));
+
+        // The ordering is transitive even where the tolerance chains up.
+        let (a, b, c) = (event_at(t), event_at(close), event_at(shifted));
+        assert!(a < b && b < c && a < c);
+        assert_eq!(a.cmp(&event_at(t)), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn speeds() {
+        let mut sim = Simulation::new(1.);
+        assert_eq!(sim.mean_speed(), 0.);
+        assert_eq!(sim.rms_speed(), 0.);
+
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (3., 4.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.).into(), (0., -1.).into(), 0.1));
+        assert!((sim.mean_speed() - 3.).abs() < 1e-12);
+        assert!((sim.rms_speed() - 13f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn scale_radii() {
+        let mut sim = Simulation::new(1.);
+        for pos in [(-0.5, 0.), (0.5, 0.)] {
+            sim.balls.push(Ball::new(pos.into(), (0., 1.).into(), 0.1));
+        }
+
+        sim.scale_radii(2.).unwrap();
+        assert!(sim.balls.iter().all(|ball| (ball.r - 0.2).abs() < 1e-12));
+        // Radii of 0.6 would overlap both each other and the wall.
+        assert!(sim.scale_radii(3.).is_err());
+        assert!(sim.balls.iter().all(|ball| (ball.r - 0.2).abs() < 1e-12));
+    }
+
+    #[test]
+    fn energy_components() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (2., 1.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.).into(), (-2., 0.).into(), 0.1));
+        let (ex, ey) = sim.energy_components();
+
+        assert!((ex - 4.).abs() < 1e-12);
+        assert!((ey - 0.5).abs() < 1e-12);
+        assert!((ex + ey - sim.kinetic_energy()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn run_collisions_counted() {
+        let mut sim = glancing();
+        assert_eq!(sim.run_collisions_counted(10).unwrap(), 10);
+
+        let max_time = sim.global_time + 2.;
+        sim.set_max_time(Some(max_time));
+        let before = sim.collision_count;
+        let (completed, err) = sim.run_collisions_counted(1_000).unwrap_err();
+
+        assert!(matches!(err, DynamicsError::TimeLimitExceeded));
+        assert!(completed > 0 && completed < 1_000);
+        assert_eq!(completed, sim.collision_count - before);
+    }
+
+    #[test]
+    fn min_distances() {
+        let mut sim = Simulation::new(1.);
+        assert_eq!(sim.min_pair_distance().unwrap(), None);
+        assert_eq!(sim.min_wall_distance().unwrap(), None);
+
+        for pos in [(-0.5, 0.), (0.5, 0.), (0.25, 0.)] {
+            sim.balls.push(Ball::new(pos.into(), (0., 0.).into(), 0.1));
+        }
+        assert!((sim.min_pair_distance().unwrap().unwrap() - 0.05).abs() < 1e-12);
+        assert!((sim.min_wall_distance().unwrap().unwrap() - 0.4).abs() < 1e-12);
+
+        sim.container.inner_radius = Some(0.3);
+        assert!((sim.min_wall_distance().unwrap().unwrap() + 0.15).abs() < 1e-12);
+    }
+
+    #[test]
+    fn save_and_load_state() {
+        let mut uninterrupted = Simulation::thermalized(1., 20, 0.05, 1., 3).unwrap();
+        for (k, ball) in uninterrupted.balls.iter_mut().enumerate() {
+            ball.payload = k as f64 + 0.25;
+        }
+        uninterrupted.run_collisions(500).unwrap();
+        let mut resumed = Simulation::new(1.);
+        resumed.load_state(uninterrupted.save_state()).unwrap();
+        assert_eq!(resumed.payloads(), uninterrupted.payloads());
+
+        uninterrupted.run_collisions(500).unwrap();
+        resumed.run_collisions(500).unwrap();
+        assert_eq!(resumed.global_time, uninterrupted.global_time);
+        for (p, q) in resumed.balls.iter().zip(uninterrupted.balls.iter()) {
+            assert_eq!(p.pos(), q.pos());
+            assert_eq!(p.vel(), q.vel());
+        }
+
+        let mut bad_state = resumed.save_state();
+        bad_state.2.clear();
+        assert!(resumed.load_state(bad_state).is_err());
+        assert_eq!(resumed.balls.len(), 20);
+    }
+
+    #[test]
+    fn momentum() {
+        let mut sim = Simulation::new(1.);
+        assert_eq!(sim.momentum_direction().unwrap(), None);
+
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (1., 2.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.).into(), (-2., 1.).into(), 0.1));
+        sim.balls.push(Ball::frozen((0., 0.5).into(), 0.1));
+
+        // The net momentum is (-1, 3), and the frozen ball does not contribute.
+        assert!((sim.momentum_magnitude().unwrap() - 10f64.sqrt()).abs() < 1e-12);
+        let direction = sim.momentum_direction().unwrap().unwrap();
+        assert!((direction - 3f64.atan2(-1.)).abs() < 1e-12);
+
+        sim.py_to_com_frame().unwrap();
+        assert!(sim.momentum_magnitude().unwrap() < 1e-12);
+    }
+
+    #[test]
+    fn pair_collision_time() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.05).into(), (-1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0., -0.5).into(), (0., -1.).into(), 0.1));
+        sim.global_time = 1.;
+
+        // The centres close at speed 2 and touch once 0.0375 + 0.05^2 = 0.2^2.
+        let expected = 1. + (1. - 0.0375f64.sqrt()) / 2.;
+        let t = sim.pair_collision_time(0, 1).unwrap().unwrap();
+        assert!((t - expected).abs() < 1e-12);
+        assert_eq!(sim.pair_collision_time(1, 2).unwrap(), None);
+        assert!(sim.pair_collision_time(0, 3).is_err());
+        assert!(sim.pair_collision_time(1, 1).is_err());
+    }
+
+    #[test]
+    fn hole() {
+        // The first ball reaches the wall at an angle of asin(1 / 3) within the
+        // hole, and the second, more slowly, on the far side.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.3).into(), (1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0., -0.3).into(), (-0.5, 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        sim.py_set_hole(0., 0.5).unwrap();
+
+        let event = sim.step_with_data().unwrap();
+        assert!(matches!(event, DataEvent::Escape { index: 0, .. }));
+        assert_eq!(sim.escaped_count().unwrap(), 1);
+        let expected = (0.81f64 - 0.09).sqrt();
+        assert!((sim.escape_times().unwrap()[0] - expected).abs() < 1e-5);
+
+        sim.step_through_collision().unwrap();
+        assert_eq!(sim.balls.len(), 1);
+        assert_eq!(sim.escaped_count().unwrap(), 1);
+        assert!(sim.balls[0].vel().x > 0.);
+    }
+
+    #[test]
+    fn velocity_histogram() {
+        let mut sim = Simulation::new(1.);
+        for vel in [(0.5, 0.5), (0.5, 0.2), (-0.5, 0.5), (-0.5, -0.5), (3., 0.)] {
+            sim.balls.push(Ball::new((0., 0.).into(), vel.into(), 0.01));
+        }
+        let (counts, edges) = sim.velocity_histogram(2, 1., true).unwrap();
+
+        // Rows are vx, columns vy; the last ball is off the grid.
+        assert_eq!(counts, vec![1, 1, 0, 2]);
+        assert_eq!(edges, vec![-1., 0., 1.]);
+    }
+
+    #[test]
+    fn perturb_ball() {
+        let mut original = Simulation::thermalized(1., 20, 0.05, 1., 11).unwrap();
+        let mut perturbed = Simulation::thermalized(1., 20, 0.05, 1., 11).unwrap();
+        perturbed.perturb_ball(0, (1e-9, 0.), (0., 0.)).unwrap();
+        perturbed.initialise().unwrap();
+        assert!(perturbed.perturb_ball(0, (5., 0.), (0., 0.)).is_err());
+        assert!(perturbed.perturb_ball(20, (0., 0.), (0., 0.)).is_err());
+
+        original.run_until_time(20.).unwrap();
+        perturbed.run_until_time(20.).unwrap();
+        let divergence = original
+            .balls
+            .iter()
+            .zip(perturbed.balls.iter())
+            .map(|(p, q)| (*p.pos() - *q.pos()).magnitude())
+            .fold(0., f64::max);
+        assert!(divergence > 1e-3);
+    }
+
+    #[test]
+    fn compressibility_factor() {
+        let mut dilute = Simulation::thermalized(1., 20, 0.005, 1., 5).unwrap();
+        let z = dilute.compressibility_factor(2_000).unwrap();
+        assert!((1. ..1.05).contains(&z));
+
+        let mut dense = Simulation::thermalized(1., 100, 0.05, 1., 5).unwrap();
+        assert!(dense.compressibility_factor(2_000).unwrap() > 1.2);
+    }
+
+    #[test]
+    fn balls_sorted_by() {
+        let mut sim = Simulation::new(1.);
+        for (pos, vel, r) in [
+            ((0.5, -0.2), (0., 2.), 0.05),
+            ((-0.5, 0.3), (1., 0.), 0.2),
+            ((0., 0.), (0., -3.), 0.1),
+        ] {
+            sim.balls.push(Ball::new(pos.into(), vel.into(), r));
+        }
+
+        assert_eq!(sim.balls_sorted_by("speed").unwrap(), vec![1, 0, 2]);
+        assert_eq!(sim.balls_sorted_by("radius").unwrap(), vec![0, 2, 1]);
+        assert_eq!(sim.balls_sorted_by("x").unwrap(), vec![1, 2, 0]);
+        assert_eq!(sim.balls_sorted_by("y").unwrap(), vec![0, 2, 1]);
+        assert!(sim.balls_sorted_by("mass").is_err());
+    }
+
+    #[test]
+    fn pressure_autocorrelation() {
+        // A ball bouncing between the wall and a frozen ball alternates wall
+        // and ball collisions, so the impulse series is 2, 0, 2, 0, ...
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (-1., 0.).into(), 0.1));
+        sim.balls.push(Ball::frozen((0.5, 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let (lags, autocorr) = sim.pressure_autocorrelation(100, 4).unwrap();
+
+        assert_eq!(lags, vec![0, 1, 2, 3, 4]);
+        // Only `100 - lag` terms contribute at each lag.
+        for (lag, value) in lags.into_iter().zip(autocorr) {
+            let expected = (-1f64).powi(lag as i32) * (100 - lag) as f64 / 100.;
+            assert!((value - expected).abs() < 1e-9);
+        }
+        assert!(sim.pressure_autocorrelation(4, 4).is_err());
+    }
+
+    #[test]
+    fn replay() {
+        let mut recorded = glancing();
+        let events: Vec<(f64, usize, Option<usize>)> = (0..6)
+            .map(|_| {
+                let event = recorded.step_with_data().unwrap();
+                let (i, j) = event.participants();
+                (event.time(), i, j)
+            })
+            .collect();
+
+        let mut replayed = glancing();
+        replayed.replay(&events, 1e-4).unwrap();
+        for (p, q) in replayed.balls.iter().zip(recorded.balls.iter()) {
+            assert!((*p.pos() - *q.pos()).magnitude() < 1e-12);
+            assert!((*p.vel() - *q.vel()).magnitude() < 1e-12);
+        }
+        // The queue is rebuilt, so the run can carry on.
+        replayed.run_collisions(5).unwrap();
+
+        // Ball 1 hits the wall, not ball 0, in the second event.
+        let mut altered = events.clone();
+        altered[1].2 = Some(0);
+        let mut mismatched = glancing();
+        let result = mismatched.replay(&altered, 1e-4);
+        assert!(matches!(
+            result,
+            Err(DynamicsError::ReplayMismatch { event: 1 })
+        ));
+        // The failed replay is undone, so the run carries on as if it had
+        // never been attempted.
+        assert_eq!(mismatched.global_time, 0.);
+        assert_eq!(mismatched.collision_count, 0);
+        mismatched.run_collisions(6).unwrap();
+        for (p, q) in mismatched.balls.iter().zip(recorded.balls.iter()) {
+            assert_eq!(p.pos(), q.pos());
+            assert_eq!(p.vel(), q.vel());
+        }
+    }
+
+    #[test]
+    fn speed_stats() {
+        let mut sim = Simulation::new(1.);
+        assert_eq!(sim.speed_stats(true).unwrap()["max"], 0.);
+
+        for vel in [(3., 4.), (0., -1.), (0., 0.)] {
+            sim.balls.push(Ball::new((0., 0.).into(), vel.into(), 0.01));
+        }
+        let stats = sim.speed_stats(true).unwrap();
+        assert_eq!(stats["min"], 0.);
+        assert_eq!(stats["max"], 5.);
+        assert!((stats["mean"] - 2.).abs() < 1e-12);
+        assert!((stats["rms"] - (26f64 / 3.).sqrt()).abs() < 1e-12);
+        assert!((stats["mean"] - sim.mean_speed()).abs() < 1e-12);
+        assert!((stats["rms"] - sim.rms_speed()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tracers() {
+        let mut sim = Simulation::new(1.);
+        for vel in [(1., 0.), (0., 1.)] {
+            sim.balls.push(Ball::new((0., 0.).into(), vel.into(), 0.01));
+        }
+        let mut tracer = Ball::new((0., 0.).into(), (4., 0.).into(), 0.01);
+        tracer.is_tracer = true;
+        sim.balls.push(tracer);
+
+        assert!((sim.py_temperature(true).unwrap() - 3.).abs() < 1e-12);
+        assert!((sim.py_temperature(false).unwrap() - 0.5).abs() < 1e-12);
+        assert!((sim.total_kinetic_energy(false).unwrap() - 1.).abs() < 1e-12);
+        assert!(sim.py_ideal_pressure(false).unwrap() < sim.py_ideal_pressure(true).unwrap());
+        assert!((sim.py_mean_speed(false).unwrap() - 1.).abs() < 1e-12);
+        assert!((sim.py_mean_speed(true).unwrap() - 2.).abs() < 1e-12);
+        assert!((sim.py_rms_speed(false).unwrap() - 1.).abs() < 1e-12);
+        assert_eq!(sim.speed_stats(false).unwrap()["max"], 1.);
+        assert_eq!(sim.speed_stats(true).unwrap()["max"], 4.);
+        assert_eq!(sim.py_energy_components(false).unwrap(), (0.5, 0.5));
+        assert_eq!(sim.py_energy_components(true).unwrap(), (8.5, 0.5));
+        let binned = |include_tracers| {
+            let hist = sim
+                .energy_distribution(0., 10., 5, include_tracers)
+                .unwrap();
+            hist.counts.iter().sum::<usize>()
+        };
+        assert_eq!((binned(false), binned(true)), (2, 3));
+        let (counts, _) = sim.velocity_histogram(4, 5., false).unwrap();
+        assert_eq!(counts.iter().sum::<usize>(), 2);
+
+        let mut resumed = Simulation::new(1.);
+        resumed.load_state(sim.save_state()).unwrap();
+        assert!(resumed.balls[2].is_tracer);
+        assert!(!resumed.balls[0].is_tracer);
+
+        // A bath with one fast tracer: leaving the tracer out lowers the
+        // measured wall pressure, just as for the ideal gas pressure.
+        pyo3::prepare_freethreaded_python();
+        let mut bath = Simulation::thermalized(1., 20, 0.03, 1., 4).unwrap();
+        bath.balls[0].is_tracer = true;
+        bath.balls[0].vel = bath.balls[0].vel * 20.;
+        bath.initialise().unwrap();
+        let with = bath.py_pressure(200, 10, true).unwrap();
+        let without = bath.py_pressure(200, 10, false).unwrap();
+        Python::with_gil(|py| {
+            let mean = |result: &HashMap<String, PyObject>| {
+                let pressures: Vec<f64> = result["pressures"].extract(py).unwrap();
+                pressures.iter().sum::<f64>() / pressures.len() as f64
+            };
+            assert!(mean(&without) < mean(&with));
+        });
+
+        // The tracer's own hits on the wall are the only ones dropped.
+        let state = bath.save_state();
+        let (with, _) = bath.wall_impulses(200, true).unwrap();
+        bath.load_state(state).unwrap();
+        let (without, _) = bath.wall_impulses(200, false).unwrap();
+        assert!(without.len() < with.len());
+        assert!(without.iter().all(|t| with.contains(t)));
+    }
+
+    #[test]
+    fn second_virial() {
+        let r = 0.02;
+        let mut sim = Simulation::thermalized(1., 100, r, 1., 9).unwrap();
+        sim.run_collisions(2_000).unwrap();
+        let b2 = sim.second_virial(10_000).unwrap();
+
+        let exact = 2. * std::f64::consts::PI * r * r;
+        // Higher virial terms push the single-point estimate a little high.
+        assert!((b2 / exact - 1.).abs() < 0.15);
+    }
+
+    #[test]
+    fn radius_range() {
+        let mut sim = Simulation::new(1.);
+        assert_eq!(sim.max_radius().unwrap(), None);
+        assert_eq!(sim.min_radius().unwrap(), None);
+
+        for (pos, r) in [((-0.5, 0.), 0.1), ((0., 0.), 0.05), ((0.5, 0.), 0.2)] {
+            sim.balls.push(Ball::new(pos.into(), (0., 0.).into(), r));
+        }
+        assert_eq!(sim.max_radius().unwrap(), Some(0.2));
+        assert_eq!(sim.min_radius().unwrap(), Some(0.05));
+    }
+
+    #[test]
+    fn wall_restitution() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.3).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        sim.py_set_wall_restitution(0.5).unwrap();
+        assert!(sim.py_set_wall_restitution(1.5).is_err());
+
+        for _ in 0..3 {
+            let event = sim.step_with_data().unwrap();
+            let DataEvent::ContainerCollision { pre, post, .. } = event else {
+                panic!("Expected a collision with the container.");
+            };
+            let normal = pre.pos().normalize();
+            let (v_pre, v_post) = (*pre.vel(), *post.vel());
+            let normal_ratio =
+                v_post.project_onto(&normal).magnitude() / v_pre.project_onto(&normal).magnitude();
+            assert!((normal_ratio - 0.5).abs() < 1e-9);
+            let tangential_change = v_post.reject_from(&normal) - v_pre.reject_from(&normal);
+            assert!(tangential_change.magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn cooling_curve() {
+        let mut elastic = Simulation::thermalized(1., 10, 0.05, 1., 2).unwrap();
+        let (counts, energies) = elastic.cooling_curve(100, 10, true).unwrap();
+        assert_eq!(counts, (0..=100).step_by(10).collect::<Vec<_>>());
+        for energy in &energies {
+            assert!((energy - energies[0]).abs() < 1e-9);
+        }
+
+        let mut lossy = Simulation::thermalized(1., 10, 0.05, 1., 2).unwrap();
+        lossy.py_set_wall_restitution(0.9).unwrap();
+        let (_, energies) = lossy.cooling_curve(100, 10, true).unwrap();
+        for pair in energies.windows(2) {
+            assert!(pair[1] < pair[0]);
+        }
+    }
+
+    #[test]
+    fn ball_next_collision() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0.5, 0.05).into(), (-1., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((0., -0.5).into(), (0., -1.).into(), 0.1));
+        sim.initialise().unwrap();
+
+        let (t, kind, partner) = sim.py_ball_next_collision(0).unwrap().unwrap();
+        assert!((t - (1. - 0.0375f64.sqrt()) / 2.).abs() < 1e-12);
+        assert_eq!((kind, partner), (PartnerKind::Ball, Some(1)));
+        let (_, kind, partner) = sim.py_ball_next_collision(1).unwrap().unwrap();
+        assert_eq!((kind, partner), (PartnerKind::Ball, Some(0)));
+        let (t, kind, partner) = sim.py_ball_next_collision(2).unwrap().unwrap();
+        assert!((t - 0.4).abs() < 1e-12);
+        assert_eq!((kind, partner), (PartnerKind::Container, None));
+        assert!(sim.py_ball_next_collision(3).is_err());
+
+        // A baffle across its path is told apart from the container.
+        sim.add_baffle((-0.5, -0.75), (0.5, -0.75)).unwrap();
+        sim.initialise().unwrap();
+        let (t, kind, partner) = sim.py_ball_next_collision(2).unwrap().unwrap();
+        assert!((t - 0.15).abs() < 1e-12);
+        assert_eq!((kind, partner), (PartnerKind::Baffle, None));
+    }
+
+    #[test]
+    fn initialise_guard() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        let err = sim.collision_times(10, 0., 2., 10, 1).err().unwrap();
+        assert!(err.to_string().contains("call initialise() first"));
+
+        sim.initialise().unwrap();
+        assert!(sim.collision_times(10, 0., 2., 10, 1).is_ok());
+        sim.reverse_velocities().unwrap();
+        assert!(sim.collision_times(10, 0., 2., 10, 1).is_err());
+        assert!(sim.nth_collision_times(2, 10, 0., 2., 10, 1).is_err());
+        assert!(sim.track_positions(10).is_err());
+        assert!(sim.py_ball_next_collision(0).is_err());
+    }
+
+    #[test]
+    fn event_times() {
+        let mut sim = Simulation::thermalized(1., 20, 0.05, 1., 4).unwrap();
+        let times = sim.event_times(200).unwrap();
+
+        assert_eq!(times.len(), 200);
+        assert!(times[0] > 0.);
+        assert!(times.windows(2).all(|pair| pair[1] > pair[0]));
+        assert_eq!(*times.last().unwrap(), sim.global_time);
+    }
+
+    #[test]
+    fn max_queue_size() {
+        let mut unbounded = Simulation::thermalized(1., 100, 0.04, 1., 6).unwrap();
+        let mut bounded = Simulation::thermalized(1., 100, 0.04, 1., 6).unwrap();
+        bounded.set_max_queue_size(Some(1_000));
+
+        let mut largest = 0;
+        for _ in 0..2_000 {
+            unbounded.step_through_collision().unwrap();
+            bounded.step_through_collision().unwrap();
+            largest = largest.max(bounded.collisions.len());
+        }
+        assert!(unbounded.collisions.len() > 2_000);
+        assert!(largest <= 1_000);
+        assert_eq!(bounded.global_time, unbounded.global_time);
+        for (p, q) in bounded.balls.iter().zip(unbounded.balls.iter()) {
+            assert_eq!(p.pos(), q.pos());
+        }
+    }
+
+    #[test]
+    fn collision_type_ratio() {
+        // Bouncing between the wall and a frozen ball, exactly every other
+        // collision is with the wall.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (-1., 0.).into(), 0.1));
+        sim.balls.push(Ball::frozen((0.5, 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let (counts, fractions) = sim.collision_type_ratio(40, 4).unwrap();
+
+        assert_eq!(counts, vec![10, 20, 30, 40]);
+        assert!(fractions.iter().all(|&fraction| fraction == 0.5));
+        assert!(sim.collision_type_ratio(4, 0).is_err());
+    }
+
+    #[test]
+    fn coverage() {
+        // On a 5 by 5 grid, 21 cells have centres inside the unit circle, and a
+        // ball bouncing along the x-axis passes through the 5 in the middle row.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let coverage = sim.coverage(4, 5).unwrap();
+
+        assert!((coverage - 5. / 21.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn get_ball() {
+        let mut sim = Simulation::new(1.);
+        sim.fill_lattice(2, 2, 0.2, 0.05, "square").unwrap();
+
+        assert!(sim
+            .get_ball(2)
+            .unwrap()
+            .approx_eq(&sim.get_balls().unwrap()[2], 0));
+        assert!(sim.get_ball(4).is_err());
+    }
+
+    #[test]
+    fn presets() {
+        let mut cradle = Simulation::newtons_cradle(5).unwrap();
+        assert_eq!(cradle.balls.len(), 5);
+        assert_eq!(*cradle.balls[0].vel(), (1., 0.).into());
+        assert!(cradle.balls[1..]
+            .iter()
+            .all(|ball| *ball.vel() == FloatVec::origin()));
+        assert!(Simulation::newtons_cradle(0).is_none());
+        assert!(Simulation::newtons_cradle(20).is_none());
+
+        // The momentum passes down the line to the rightmost ball.
+        cradle.run_collisions(4).unwrap();
+        assert!(cradle.balls[4].vel().approx_eq(&(1., 0.).into(), 4));
+
+        let head_on = Simulation::head_on(2.);
+        assert_eq!(head_on.balls.len(), 2);
+        assert_eq!(*head_on.balls[0].vel(), (2., 0.).into());
+        assert_eq!(*head_on.balls[1].vel(), (-2., 0.).into());
+        assert_eq!(head_on.collisions.len(), 3);
+    }
+
+    #[test]
+    fn time_averaged_speeds() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0.1, 0.2).into(), (0.6, -0.8).into(), 0.1));
+        sim.initialise().unwrap();
+        let speeds = sim.time_averaged_speeds(5).unwrap();
+
+        assert_eq!(speeds.len(), 1);
+        assert!((speeds[0] - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn positions_bytes() {
+        let mut sim = Simulation::new(1.);
+        sim.fill_lattice(2, 2, 0.2, 0.05, "square").unwrap();
+        let bytes = sim.snapshot_positions_bytes(None).unwrap();
+        assert_eq!(bytes.len(), 4 * 16);
+
+        let positions = Simulation::positions_from_bytes(bytes).unwrap();
+        for (ball, (x, y)) in sim.balls.iter().zip(positions) {
+            assert_eq!(ball.pos().x, x);
+            assert_eq!(ball.pos().y, y);
+        }
+        assert!(Simulation::positions_from_bytes(vec![0; 12]).is_err());
+    }
+
+    #[test]
+    fn isotropization_time() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::thermalized(1., 30, 0.05, 1., 3).unwrap();
+        for ball in sim.balls.iter_mut() {
+            ball.set_vel(FloatVec::new(ball.vel().x, 0.));
+        }
+        sim.invalidate_queue();
+        sim.initialise().unwrap();
+        assert!(sim.isotropization_time(0.1, 0).is_err());
+
+        let (count, time) = sim.isotropization_time(0.1, 5_000).unwrap();
+        assert!(count > 0);
+        assert!(time > 0.);
+        let (ex, ey) = sim.energy_components();
+        assert!((ex - ey).abs() < 0.1 * (ex + ey));
+    }
+
+    #[test]
+    fn collision_relative_speeds() {
+        let mut sim = Simulation::thermalized(1., 30, 0.05, 1., 5).unwrap();
+        let bulk = sim
+            .balls
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| (*a.vel() - *b.vel()).magnitude())
+            .collect::<Vec<f64>>();
+        let bulk_mean = bulk.iter().sum::<f64>() / bulk.len() as f64;
+
+        let hist = sim.collision_relative_speeds(3_000, 100).unwrap();
+        let total: usize = hist.counts.iter().sum();
+        let collision_mean = hist
+            .centres
+            .iter()
+            .zip(hist.counts.iter())
+            .map(|(centre, &count)| centre * count as f64)
+            .sum::<f64>()
+            / total as f64;
+
+        assert!(total > 0);
+        assert!(collision_mean > bulk_mean);
+    }
+
+    #[test]
+    fn params() {
+        let mut sim = Simulation::new(1.);
+        let mut params = sim.get_params().unwrap();
+        assert_eq!(params.len(), 5);
+        assert_eq!(params["delta"], 1e-6);
+        assert_eq!(params["force_substep"], 1e-3);
+
+        params.insert("k_b".to_string(), 2.);
+        params.insert("max_time".to_string(), 10.);
+        params.insert("force_substep".to_string(), 1e-2);
+        params.insert("harmonic_k".to_string(), 3.);
+        params.insert("max_queue_size".to_string(), 500.);
+        sim.set_params(params.clone()).unwrap();
+        assert_eq!(sim.get_params().unwrap(), params);
+        assert_eq!(sim.max_queue_size(), Some(500));
+
+        for (key, value) in [
+            ("max_queue_size", 2.5),
+            ("max_queue_size", 0.),
+            ("harmonic_k", -1.),
+            ("force_substep", 0.),
+        ] {
+            let invalid = HashMap::from([(key.to_string(), value)]);
+            assert!(sim.set_params(invalid).is_err());
+        }
+        assert_eq!(sim.get_params().unwrap(), params);
+
+        let unknown = HashMap::from([("gravity".to_string(), 1.)]);
+        assert!(sim.set_params(unknown).is_err());
+        let invalid = HashMap::from([
+            ("k_b".to_string(), 3.),
+            ("wall_restitution".to_string(), 2.),
+        ]);
+        assert!(sim.set_params(invalid).is_err());
+        assert_eq!(sim.k_b(), 2.);
+    }
+
+    #[test]
+    fn strict() {
+        let mut sim = glancing();
+        sim.set_strict(true);
+        sim.run_collisions(100).unwrap();
+
+        // A wall collision scheduled while the ball is still far from the wall.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        sim.set_strict(true);
+        let mistimed = event::CollisionEvent::new(
+            0,
+            event::CollisionPartner::Container,
+            0.1,
+            ((1., 0.).into(), FloatVec::origin()),
+        );
+        sim.collisions.push(std::cmp::Reverse(mistimed));
+        let result = sim.step_through_collision();
+
+        assert!(matches!(
+            result,
+            Err(DynamicsError::MistimedCollision { collision: 0, gap }) if (gap - 0.8).abs() < 1e-5
+        ));
+
+        // The tolerance scales with the balls, so a gap that would be lost
+        // among large balls is still caught between small ones.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.001));
+        sim.balls
+            .push(Ball::new((0.00205, 0.).into(), (0., 0.).into(), 0.001));
+        sim.initialise().unwrap();
+        sim.set_strict(true);
+        let mistimed = event::CollisionEvent::new(
+            0,
+            event::CollisionPartner::Ball(1),
+            0.,
+            ((1., 0.).into(), FloatVec::origin()),
+        );
+        sim.collisions.push(std::cmp::Reverse(mistimed));
+        let result = sim.step_through_collision();
+        assert!(matches!(
+            result,
+            Err(DynamicsError::MistimedCollision { collision: 0, gap }) if (gap - 5e-5).abs() < 1e-9
+        ));
+    }
+
+    #[test]
+    fn density_grid() {
+        // Under gravity, and with lossy walls to carry away the energy released
+        // as they fall, the balls spend longer in the bottom half of the
+        // container than the top.
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::thermalized(1., 10, 0.05, 1., 2).unwrap();
+        Python::with_gil(|py| {
+            let force = py.eval_bound("lambda x, y: (0., -4.)", None, None);
+            sim.py_set_force_field(Some(force.unwrap().unbind()), 1e-2)
+                .unwrap();
+        });
+        sim.set_wall_restitution(0.7);
+        let density = sim.density_grid(1_000, 4).unwrap();
+
+        let (corners, inside): (Vec<usize>, Vec<usize>) =
+            (0..16).partition(|&cell| density[cell].is_nan());
+        assert_eq!(corners, vec![0, 3, 12, 15]);
+        let total: f64 = inside.iter().map(|&cell| density[cell]).sum();
+        assert!((total - 1.).abs() < 1e-9);
+        let bottom: f64 = density[..8].iter().filter(|d| !d.is_nan()).sum();
+        assert!(bottom > 0.75);
+    }
+
+    #[test]
+    fn next_collision_data() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (2., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let data = sim.next_collision_data().unwrap();
+
+        Python::with_gil(|py| {
+            let get = |key: &str| data[key].bind(py);
+            assert_eq!(get("type").extract::<String>().unwrap(), "container");
+            assert_eq!(
+                get("indices").extract::<(usize, Option<usize>)>().unwrap(),
+                (0, None)
+            );
+            let pre: Vec<(f64, f64)> = get("pre_vels").extract().unwrap();
+            let post: Vec<(f64, f64)> = get("post_vels").extract().unwrap();
+            // The ball bounces straight back, reversing its momentum.
+            assert!((pre[0].0 - post[0].0 - 4.).abs() < 1e-9);
+            assert!(post[0].1.abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn positions_precision() {
+        let mut sim = Simulation::new(1.);
+        sim.balls.push(Ball::new(
+            (0.123456, -0.654321).into(),
+            (1., 0.).into(),
+            0.1,
+        ));
+        let bytes = sim.snapshot_positions_bytes(Some(3)).unwrap();
+
+        let positions = Simulation::positions_from_bytes(bytes).unwrap();
+        assert_eq!(positions, vec![(0.123, -0.654)]);
+    }
+
+    #[test]
+    fn contact_distance_distribution() {
+        // Every ball has radius 0.05, so the centres should be 0.1 apart at
+        // every collision, up to the small gap left by the `delta` fudge.
+        let mut sim = Simulation::thermalized(1., 30, 0.05, 1., 4).unwrap();
+        let hist = sim
+            .contact_distance_distribution(1_000, 20, 0.09, 0.11)
+            .unwrap();
+
+        let total: usize = hist.counts.iter().sum();
+        assert!(total > 0);
+        assert_eq!(hist.counts[9] + hist.counts[10], total);
+    }
+
+    #[test]
+    fn run_async() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let sim = Simulation::thermalized(1., 30, 0.05, 1., 6).unwrap();
+            let sim = Bound::new(py, sim).unwrap();
+            let mut handle = Simulation::run_async(&sim, usize::MAX).unwrap();
+            assert!(sim.borrow().core.is_none());
+
+            py.allow_threads(|| std::thread::sleep(std::time::Duration::from_millis(50)));
+            assert!(!handle.is_done());
+            handle.cancel();
+            let started = std::time::Instant::now();
+            let completed = handle.join(py).unwrap();
+
+            assert!(started.elapsed() < std::time::Duration::from_secs(1));
+            assert!(completed > 0);
+            assert_eq!(handle.progress(), completed);
+            assert_eq!(sim.borrow().balls.len(), 30);
+            assert_eq!(sim.borrow().collision_count, completed);
+            assert!(handle.join(py).is_err());
+        });
+    }
+
+    #[test]
+    fn velocity_moments() {
+        // One ball in four moving with speed 3 is a scaled Bernoulli
+        // distribution with p = 1/4: variance 27/16, skewness 2/sqrt(3) and
+        // excess kurtosis -2/3.
+        let mut sim = Simulation::new(1.);
+        for vel in [(0., 0.), (0., 0.), (0., 0.)] {
+            sim.balls.push(Ball::new((0., 0.).into(), vel.into(), 0.01));
+        }
+        assert!(sim.velocity_moments(true).is_err());
+
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (0., -3.).into(), 0.01));
+        let moments = sim.velocity_moments(true).unwrap();
+        assert!((moments["mean"] - 0.75).abs() < 1e-12);
+        assert!((moments["variance"] - 27. / 16.).abs() < 1e-12);
+        assert!((moments["skewness"] - 2. / 3f64.sqrt()).abs() < 1e-12);
+        assert!((moments["kurtosis"] + 2. / 3.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn payloads() {
+        let mut sim = glancing();
+        for (k, ball) in sim.balls.iter_mut().enumerate() {
+            ball.payload = k as f64 + 0.5;
+        }
+        let before = sim.payloads();
+        sim.run_collisions(50).unwrap();
+
+        assert_eq!(sim.payloads(), before);
+        assert_eq!(sim.get_balls().unwrap()[0].payload, 0.5);
+    }
+
+    #[test]
+    fn run_to_columns() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = glancing();
+        let columns = sim.run_to_columns(20).unwrap();
+
+        assert_eq!(columns.len(), 12);
+        Python::with_gil(|py| {
+            for column in columns.values() {
+                assert_eq!(column.bind(py).len().unwrap(), 20);
+            }
+            let ball_j: Vec<i64> = columns["ball_j"].extract(py).unwrap();
+            let pre_vx_j: Vec<f64> = columns["pre_vx_j"].extract(py).unwrap();
+            for (j, vx) in ball_j.into_iter().zip(pre_vx_j) {
+                assert_eq!(j == -1, vx.is_nan());
+            }
+        });
+    }
+
+    #[test]
+    fn container_collision_normal() {
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.3).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let DataEvent::ContainerCollision { pre, normal, .. } = sim.step_with_data().unwrap()
+        else {
+            panic!("Expected a collision with the container.");
+        };
+
+        assert!(normal.approx_eq(&(*pre.pos() * -1.).normalize(), 1));
+    }
+
+    #[test]
+    fn temperature_grid() {
+        // A frozen ball at the centre keeps a hot ball on the positive x-axis
+        // apart from a cold one on the negative y-axis. On a 4 by 4 grid, the
+        // hot ball visits cells 10 and 11 and the cold one cells 2 and 6.
+        let mut sim = Simulation::new(1.);
+        sim.balls.push(Ball::frozen(FloatVec::origin(), 0.2));
+        sim.balls
+            .push(Ball::new((0.5, 0.).into(), (4., 0.).into(), 0.05));
+        sim.balls
+            .push(Ball::new((0., -0.5).into(), (0., 1.).into(), 0.05));
+        sim.initialise().unwrap();
+        let temperatures = sim.temperature_grid(50, 4).unwrap();
+
+        for cell in [10, 11] {
+            assert!((temperatures[cell] - 8.).abs() < 1e-6);
+        }
+        for cell in [2, 6] {
+            assert!((temperatures[cell] - 0.5).abs() < 1e-6);
+        }
+        assert!(temperatures[5].is_nan());
+    }
+
+    #[test]
+    fn scale_container() {
+        let mut sim = Simulation::new(1.);
+        for pos in [(-0.6, 0.), (0.6, 0.), (0., 0.4)] {
+            sim.balls.push(Ball::new(pos.into(), (0., 1.).into(), 0.05));
+        }
+        let before: Vec<FloatVec> = sim.balls.iter().map(|ball| *ball.pos()).collect();
+
+        sim.scale_container(0.5, true).unwrap();
+        assert_eq!(sim.container.r, 0.5);
+        for (ball, pos) in sim.balls.iter().zip(before.iter()) {
+            assert!(ball.pos().approx_eq(&(*pos * 0.5), 1));
+        }
+        // The balls would be left outside a container of radius 0.25.
+        assert!(sim.scale_container(0.5, false).is_err());
+        assert_eq!(sim.container.r, 0.5);
+    }
+
+    #[test]
+    fn pressure_convergence() {
+        // A ball bouncing across a diameter imparts an impulse of 2 every 1.8,
+        // spread over a wall of length 2 pi.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (1., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let (counts, pressures) = sim.pressure_convergence(400, 4, true).unwrap();
+
+        assert_eq!(counts, vec![100, 200, 300, 400]);
+        let expected = 2. / (1.8 * 2. * std::f64::consts::PI);
+        assert!((pressures[3] - expected).abs() < 0.01 * expected);
+        assert!((pressures[3] - pressures[2]).abs() < (pressures[0] - expected).abs());
+    }
+
+    #[test]
+    fn baffle() {
+        // A ball moving straight up hits a baffle along `y = x` when its
+        // centre is `r` from the line, at `y = -r sqrt(2)`, and is turned to
+        // move along the x-axis.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., -0.5).into(), (0., 1.).into(), 0.05));
+        sim.add_baffle((-0.5, -0.5), (0.5, 0.5)).unwrap();
+        assert!(sim.add_baffle((0.2, 0.2), (0.2, 0.2)).is_err());
+        sim.initialise().unwrap();
+        let event = sim.step_with_data().unwrap();
+
+        let DataEvent::BaffleCollision {
+            time, baffle, post, ..
+        } = event
+        else {
+            panic!("Expected a collision with the baffle.");
+        };
+        assert_eq!(baffle, 0);
+        assert!((time - (0.5 - 0.05 * 2f64.sqrt())).abs() < 1e-6);
+        assert!((*post.vel() - (1., 0.).into()).magnitude() < 1e-12);
+
+        // The ball never passes through the baffle.
+        sim.run_collisions(50).unwrap();
+        assert!(sim.balls[0].pos().x > sim.balls[0].pos().y);
+
+        // Collisions with the baffle are not counted as hits on the wall.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., -0.5).into(), (0., 1.).into(), 0.05));
+        sim.add_baffle((-0.5, -0.5), (0.5, 0.5)).unwrap();
+        sim.initialise().unwrap();
+        assert_eq!(sim.collision_type_ratio(2, 2).unwrap().1, vec![0., 0.5]);
+
+        // Pending collisions with baffles survive a save and load exactly.
+        sim.balls
+            .push(Ball::new((0.3, -0.5).into(), (-0.7, 0.4).into(), 0.05));
+        sim.initialise().unwrap();
+        sim.run_collisions(7).unwrap();
+        let mut resumed = Simulation::new(1.);
+        assert!(resumed.load_state(sim.save_state()).is_err());
+        resumed.add_baffle((-0.5, -0.5), (0.5, 0.5)).unwrap();
+        resumed.load_state(sim.save_state()).unwrap();
+        sim.run_collisions(50).unwrap();
+        resumed.run_collisions(50).unwrap();
+        assert_eq!(resumed.global_time, sim.global_time);
+        for (p, q) in resumed.balls.iter().zip(sim.balls.iter()) {
+            assert_eq!(p.pos(), q.pos());
+            assert_eq!(p.vel(), q.vel());
+        }
+    }
+
+    #[test]
+    fn export_animation_json() {
+        pyo3::prepare_freethreaded_python();
+        let path =
+            std::env::temp_dir().join(format!("eight_ball_animation_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut sim = glancing();
+        sim.export_animation_json(path, 2., 12.5).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let frames = json["frames"].as_array().unwrap();
+        assert_eq!(frames.len(), 25);
+        assert_eq!(json["radii"].as_array().unwrap().len(), sim.balls.len());
+        assert_eq!(json["container"]["r"], 1.);
+        assert_eq!(
+            frames[0]["positions"].as_array().unwrap().len(),
+            sim.balls.len()
+        );
+        assert!(
+            (frames[1]["t"].as_f64().unwrap() - frames[0]["t"].as_f64().unwrap() - 0.08).abs()
+                < 1e-12
+        );
+
+        assert!(sim
+            .export_animation_json("/nonexistent/animation.json", 1., 1.)
+            .is_err());
+    }
+
+    #[test]
+    fn free_path_distribution() {
+        // The free paths of a single ball on a chord a distance 0.5 from the
+        // centre are all the length of the chord, whatever its speed.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.5).into(), (3., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let chord = 2. * (0.81f64 - 0.25).sqrt();
+        let hist = sim
+            .free_path_distribution(10, 3, chord - 1.5, chord + 1.5)
+            .unwrap();
+        assert_eq!(hist.counts, vec![0, 9, 0]);
+
+        // Pooled over a gas, the mean free path is set by the density.
+        let mut sim = Simulation::thermalized(1., 40, 0.01, 1., 7).unwrap();
+        sim.initialise().unwrap();
+        let hist = sim.free_path_distribution(4000, 200, 0., 4.).unwrap();
+        let total: usize = hist.counts.iter().sum();
+        let mean = hist
+            .counts
+            .iter()
+            .zip(hist.edges.windows(2))
+            .map(|(&count, edges)| count as f64 * 0.5 * (edges[0] + edges[1]))
+            .sum::<f64>()
+            / total as f64;
+        // Ball-ball collisions alone give a mean free path of
+        // `1 / (4 sqrt(2) n r)` in 2D, as two disks meet across a width of
+        // `4 r`. Paths are also ended by the wall, which
+        // the centres meet at a rate `2 <v> / (pi R)` per ball, where `R` is
+        // the radius they can reach, i.e. after a mean chord of `pi R / 2`.
+        // The rates add, so the mean path is the harmonic combination.
+        let reach = 1. - 0.01;
+        let density = 40. / (std::f64::consts::PI * reach * reach);
+        let between_balls = 1. / (4. * 2f64.sqrt() * density * 0.01);
+        let chord = std::f64::consts::PI * reach / 2.;
+        let expected = 1. / (1. / between_balls + 1. / chord);
+        assert!(
+            (mean - expected).abs() < 0.05 * expected,
+            "mean {mean}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn add_ball_after_initialise() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = glancing();
+        sim.py_next_collision().unwrap();
+        Python::with_gil(|py| {
+            let ball = Ball::new((0., -0.5).into(), (0., 1.).into(), 0.1);
+            sim.add_balls(vec![Py::new(py, ball).unwrap()]).unwrap();
+        });
+        assert!(sim.py_next_collision().is_err());
+        assert!(sim.py_run_until_time(1.).is_err());
+        let err = sim.collision_pairs(5).err().unwrap();
+        assert!(err.to_string().contains("call initialise() first"));
+
+        sim.initialise().unwrap();
+        sim.py_next_collision().unwrap();
+        assert_eq!(sim.collision_pairs(5).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn energy_fluctuations() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::thermalized(1., 20, 0.05, 1., 3).unwrap();
+        let (mean, variance, series) = sim.energy_fluctuations(200, 20, true, true).unwrap();
+        assert!((mean - sim.kinetic_energy()).abs() < 1e-9 * mean);
+        assert!(variance < 1e-18 * mean * mean);
+        assert_eq!(series.unwrap().len(), 20);
+
+        // In a harmonic trap, kinetic energy is traded with potential energy.
+        sim.set_central_harmonic(Some(5.));
+        let (_, variance, series) = sim.energy_fluctuations(200, 20, false, true).unwrap();
+        assert!(variance > 1e-6 * mean * mean);
+        assert!(series.is_none());
+        assert!(sim.energy_fluctuations(10, 0, false, true).is_err());
+
+        // The samples are spread over the whole run, remainder included.
+        let before = sim.collision_count;
+        let (_, _, series) = sim.energy_fluctuations(23, 5, true, true).unwrap();
+        assert_eq!(series.unwrap().len(), 5);
+        assert_eq!(sim.collision_count - before, 23);
+    }
+
+    #[test]
+    fn detect_tunneling() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::thermalized(1., 30, 0.05, 1., 11).unwrap();
+        assert!(sim.detect_tunneling(500, 1e-9).unwrap().is_empty());
+
+        // A resting pair placed on top of each other is never collided, so it
+        // is reported after every step.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0.1, 0.2).into(), (0.6, -0.8).into(), 0.1));
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (0., 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((-0.4, 0.).into(), (0., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        let tunneled = sim.detect_tunneling(3, 1e-9).unwrap();
+        assert_eq!(tunneled.len(), 3);
+        assert!(tunneled.iter().all(|&(i, j, _)| (i, j) == (1, 2)));
+        assert!(tunneled[0].2 < tunneled[2].2);
+    }
+
+    #[test]
+    fn time_scale() {
+        pyo3::prepare_freethreaded_python();
+        let mut unscaled = Simulation::thermalized(1., 10, 0.05, 1., 5).unwrap();
+        let mut scaled = Simulation::thermalized(1., 10, 0.05, 1., 5).unwrap();
+        scaled.py_set_time_scale(2.).unwrap();
+        assert!(scaled.py_set_time_scale(0.).is_err());
+
+        let times = unscaled.event_times(50).unwrap();
+        let scaled_times = scaled.event_times(50).unwrap();
+        for (t, scaled_t) in times.iter().zip(scaled_times) {
+            assert_eq!(scaled_t, 2. * t);
+        }
+        let (wall_times, _) = unscaled.wall_impulses(50, true).unwrap();
+        let (scaled_wall_times, _) = scaled.wall_impulses(50, true).unwrap();
+        for (t, scaled_t) in wall_times.iter().zip(scaled_wall_times) {
+            assert_eq!(scaled_t, 2. * t);
+        }
+        assert_eq!(scaled.global_time, unscaled.global_time);
+        assert_eq!(scaled.time().unwrap(), 2. * unscaled.time().unwrap());
+        assert_eq!(scaled.get_params().unwrap()["time_scale"], 2.);
+
+        let hist = unscaled.nth_collision_times(5, 200, 0., 1., 10, 1).unwrap();
+        let scaled_hist = scaled.nth_collision_times(5, 200, 0., 2., 10, 1).unwrap();
+        assert_eq!(scaled_hist.counts, hist.counts);
+
+        let pressure = unscaled.py_pressure(20, 5, true).unwrap();
+        let scaled_pressure = scaled.py_pressure(20, 5, true).unwrap();
+        Python::with_gil(|py| {
+            let windows: Vec<f64> = pressure["times"].extract(py).unwrap();
+            let scaled_windows: Vec<f64> = scaled_pressure["times"].extract(py).unwrap();
+            for (t, scaled_t) in windows.iter().zip(scaled_windows) {
+                assert_eq!(scaled_t, 2. * t);
+            }
+            let pressures: Vec<f64> = pressure["pressures"].extract(py).unwrap();
+            let scaled_pressures: Vec<f64> = scaled_pressure["pressures"].extract(py).unwrap();
+            assert_eq!(scaled_pressures, pressures);
+        });
+
+        let path =
+            std::env::temp_dir().join(format!("eight_ball_time_scale_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        scaled.export_animation_json(path, 1., 4.).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let frames = json["frames"].as_array().unwrap();
+        assert!(
+            (frames[1]["t"].as_f64().unwrap() - frames[0]["t"].as_f64().unwrap() - 0.5).abs()
+                < 1e-12
+        );
+
+        unscaled.py_set_hole(0., 0.5).unwrap();
+        scaled.py_set_hole(0., 0.5).unwrap();
+        while unscaled.escaped_count().unwrap() == 0 {
+            unscaled.py_next_collision().unwrap();
+            scaled.py_next_collision().unwrap();
+        }
+        let escapes = unscaled.escape_times().unwrap();
+        for (t, scaled_t) in escapes.iter().zip(scaled.escape_times().unwrap()) {
+            assert_eq!(scaled_t, 2. * t);
+        }
+    }
+
+    #[test]
+    fn shear_viscosity() {
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::thermalized(1., 40, 0.04, 1., 13).unwrap();
+        sim.run_collisions(500).unwrap();
+        let mut twin = Simulation::thermalized(1., 40, 0.04, 1., 13).unwrap();
+        twin.run_collisions(500).unwrap();
+        let (stresses, window) = sim.shear_stress_series(4000, None).unwrap();
+        let n = stresses.len() as f64;
+        let mean = stresses.iter().sum::<f64>() / n;
+        let rms = (stresses.iter().map(|s| s * s).sum::<f64>() / n).sqrt();
+        assert!(mean.abs() < 0.05 * rms, "mean {mean}, rms {rms}");
+
+        // The integrated stress is the change in the Helfand moment
+        // `sum (x - x_0) v_y`, so each average is the change in the moment of
+        // an identical run across the window. Stopping the twin at the window
+        // edges rounds its positions differently, and the chaos of the gas
+        // amplifies that, so only the first hundred windows are compared.
+        let moment = |sim: &Simulation| -> f64 {
+            sim.balls
+                .iter()
+                .map(|ball| (ball.pos().x - sim.container.centre.x) * ball.vel().y)
+                .sum()
+        };
+        let start = twin.global_time;
+        let area = twin.container.area();
+        let mut before = moment(&twin);
+        for (k, stress) in stresses.iter().take(100).enumerate() {
+            twin.run_until_time(start + (k + 1) as f64 * window)
+                .unwrap();
+            let after = moment(&twin);
+            let expected = (after - before) / (area * window);
+            assert!(
+                (stress - expected).abs() < 1e-5 * rms,
+                "{stress} {expected}"
+            );
+            before = after;
+        }
+
+        // The first Chapman-Enskog approximation for dilute hard disks of
+        // diameter `d` is `sqrt(m k_B T / pi) / 2d`. The gas here is small and
+        // bounded by walls, so only rough agreement is expected.
+        let (lags, autocorr, viscosity) = sim.shear_viscosity(2000, 10).unwrap();
+        assert_eq!(lags.len(), 11);
+        assert!(autocorr[0] > 0.);
+        let kt = sim.k_b() * sim.temperature();
+        let dilute = (kt / std::f64::consts::PI).sqrt() / (2. * 0.08);
+        assert!(
+            (0.5 * dilute..2. * dilute).contains(&viscosity),
+            "viscosity {viscosity}, dilute {dilute}"
+        );
+        assert!(sim.shear_viscosity(10, 10).is_err());
+    }
+
+    #[test]
+    fn velocity_entropy() {
+        let mut concentrated = Simulation::new(1.);
+        let mut broadened = Simulation::new(1.);
+        for k in 0..16 {
+            let spread = (k as f64 / 16. - 0.5, (k % 4) as f64 / 4. - 0.5);
+            concentrated.balls.push(Ball::new(
+                (0., 0.).into(),
+                (0.1 * spread.0, 0.1 * spread.1).into(),
+                0.01,
+            ));
+            broadened
+                .balls
+                .push(Ball::new((0., 0.).into(), spread.into(), 0.01));
+        }
+        let concentrated_entropy = concentrated.velocity_entropy(8, 1., true).unwrap();
+        let broadened_entropy = broadened.velocity_entropy(8, 1., true).unwrap();
+
+        assert!(broadened_entropy > concentrated_entropy);
+        // At most one ball per cell gives the maximum, `ln 16`.
+        assert!(broadened_entropy <= 16f64.ln() + 1e-12);
+        assert_eq!(
+            Simulation::new(1.).velocity_entropy(8, 1., true).unwrap(),
+            0.
+        );
+    }
+
+    #[test]
+    fn simultaneous_collisions() {
+        // A cradle with gaps only ever has one contact at a time.
+        let mut spaced = Simulation::newtons_cradle(3).unwrap();
+        spaced.run_collisions(2).unwrap();
+        assert_eq!(spaced.simultaneous_collisions, 0);
+
+        // A ball striking two touching balls is resolved as two collisions in
+        // turn, the second straight after the first. For equal masses in a
+        // line this still passes the velocity down the row.
+        let mut touching = Simulation::new(1.);
+        touching
+            .balls
+            .push(Ball::new((-0.4, 0.).into(), (1., 0.).into(), 0.05));
+        touching
+            .balls
+            .push(Ball::new((0., 0.).into(), (0., 0.).into(), 0.05));
+        touching
+            .balls
+            .push(Ball::new((0.1, 0.).into(), (0., 0.).into(), 0.05));
+        touching.initialise().unwrap();
+        touching.run_collisions(2).unwrap();
+        assert_eq!(touching.simultaneous_collisions_detected().unwrap(), 1);
+        assert!(touching.balls[0].vel().magnitude() < 1e-12);
+        assert!(touching.balls[1].vel().magnitude() < 1e-12);
+        assert!((*touching.balls[2].vel() - (1., 0.).into()).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn first_wall_times() {
+        pyo3::prepare_freethreaded_python();
+        // Balls at the centre moving radially outwards at speeds 1, 1.5, 3 and
+        // 4.5 reach the wall, 0.9 away, at times 0.9, 0.6, 0.3 and 0.2.
+        let mut sim = Simulation::new(1.);
+        for (k, speed) in [1., 1.5, 3., 4.5].into_iter().enumerate() {
+            let angle = k as f64;
+            let vel = FloatVec::new(speed * angle.cos(), speed * angle.sin());
+            sim.balls.push(Ball::new((0., 0.).into(), vel, 0.1));
+        }
+        sim.balls.push(Ball::frozen((0.5, 0.).into(), 0.1));
+        let result = sim.first_wall_times(4).unwrap();
+
+        Python::with_gil(|py| {
+            let times: Vec<f64> = result["times"].extract(py).unwrap();
+            for (t, expected) in times.iter().zip([0.9, 0.6, 0.3, 0.2]) {
+                assert!((t - expected).abs() < 1e-5);
+            }
+            let counts: Vec<usize> = result["counts"].extract(py).unwrap();
+            assert_eq!(counts, vec![1, 1, 1, 1]);
+            let excluded: usize = result["excluded"].extract(py).unwrap();
+            assert_eq!(excluded, 1);
+        });
+        assert_eq!(sim.global_time, 0.);
+    }
+
+    #[test]
+    fn sectoral_pressure() {
+        // A beam of parallel balls heading along the x-axis all strike the
+        // wall just above it, in the first of eight sectors.
+        let mut sim = Simulation::new(1.);
+        for k in 0..5 {
+            let y = 0.05 + 0.05 * k as f64;
+            sim.balls
+                .push(Ball::new((0., y).into(), (1., 0.).into(), 0.01));
+        }
+        sim.initialise().unwrap();
+        let pressures = sim.sectoral_pressure(5, 8, true).unwrap();
+
+        assert_eq!(pressures.len(), 8);
+        assert!(pressures[0] > 0.);
+        assert!(pressures[1..].iter().all(|&p| p == 0.));
+        assert!(sim.sectoral_pressure(5, 0, true).is_err());
+    }
+
+    #[test]
+    fn annular_simulation() {
+        let sim = Simulation::py_new(1., Some(0.4)).unwrap();
+        assert_eq!(sim.container.inner_radius, Some(0.4));
+        assert_eq!(
+            Simulation::py_new(1., None).unwrap().container.inner_radius,
+            None
+        );
+        for inner in [0., -0.1, 1., 1.5, f64::NAN] {
+            assert!(Simulation::py_new(1., Some(inner)).is_err());
+        }
+    }
+
+    #[test]
+    fn wall_angular_impulse() {
+        let mut sim = Simulation::thermalized(1., 20, 0.05, 1., 17).unwrap();
+        let changes = sim.wall_angular_impulse(300).unwrap();
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|dl| dl.abs() < 1e-12));
+
+        // The inner wall of an annulus is central too.
+        let mut annulus = Simulation::new(1.);
+        annulus.container.inner_radius = Some(0.3);
+        annulus
+            .balls
+            .push(Ball::new((0.5, 0.2).into(), (-0.6, -0.8).into(), 0.05));
+        annulus.initialise().unwrap();
+        let changes = annulus.wall_angular_impulse(20).unwrap();
+        assert_eq!(changes.len(), 20);
+        assert!(changes.iter().all(|dl| dl.abs() < 1e-12));
+    }
+
+    #[test]
+    fn local_pressure_grid() {
+        // In a uniform gas the pressure is the same in every cell clear of
+        // the wall: the four central cells of a 4 by 4 grid.
+        let mut sim = Simulation::thermalized(1., 60, 0.04, 1., 19).unwrap();
+        sim.run_collisions(1000).unwrap();
+        let pressures = sim.local_pressure_grid(10000, 4).unwrap();
+
+        let central = [5, 6, 9, 10].map(|cell| pressures[cell]);
+        let mean = central.iter().sum::<f64>() / 4.;
+        assert!(mean > 0.);
+        for p in central {
+            assert!((p - mean).abs() < 0.15 * mean, "{central:?}");
+        }
+        assert!(pressures[0].is_nan());
+    }
+
+    #[test]
+    fn run_async_busy() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let sim = Simulation::thermalized(1., 30, 0.05, 1., 6).unwrap();
+            let sim = Bound::new(py, sim).unwrap();
+            let handle = Simulation::run_async(&sim, usize::MAX).unwrap();
+            let err = sim.call_method0("ball_count").unwrap_err();
+            assert!(err.to_string().contains("running in the background"));
+            assert!(sim.getattr("global_time").is_err());
+            assert!(sim.call_method0("initialise").is_err());
+            assert!(Simulation::run_async(&sim, 10).is_err());
+            assert!(sim.getattr("__class__").is_ok());
+
+            // Dropping the handle cancels the run and restores the state.
+            drop(handle);
+            let count: usize = sim.call_method0("ball_count").unwrap().extract().unwrap();
+            assert_eq!(count, 30);
+        });
+    }
+
+    #[test]
+    fn force_from_rest() {
+        // A ball at rest falls under gravity until it hits the floor.
+        pyo3::prepare_freethreaded_python();
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (0., 0.).into(), 0.1));
+        let gravity = Python::with_gil(|py| {
+            py.eval_bound("lambda x, y: (0., -1.)", None, None)
+                .unwrap()
+                .unbind()
+        });
+        sim.set_force(Some(gravity), 1e-3);
+        sim.initialise().unwrap();
+        sim.step_through_collision().unwrap();
+        // Falling 0.9 from rest takes sqrt(1.8).
+        assert!((sim.global_time - 1.8f64.sqrt()).abs() < 1e-2);
+        assert!(sim.balls[0].pos().y < -0.85);
+
+        // With no force at all where it rests, it never collides.
+        let mut sim = Simulation::new(1.);
+        sim.balls
+            .push(Ball::new((0., 0.).into(), (0., 0.).into(), 0.1));
+        sim.set_central_harmonic(Some(1.));
+        sim.initialise().unwrap();
+        assert!(matches!(
+            sim.step_through_collision(),
+            Err(DynamicsError::SimulationFailure)
+        ));
+    }
+
+    #[test]
+    fn run_until_time_at_rest() {
+        let mut sim = Simulation::new(1.);
+        sim.balls.push(Ball::frozen((0.5, 0.).into(), 0.1));
+        sim.balls
+            .push(Ball::new((-0.5, 0.).into(), (0., 0.).into(), 0.1));
+        sim.initialise().unwrap();
+        assert_eq!(sim.run_until_time(2.).unwrap(), 0);
+        assert_eq!(sim.global_time, 2.);
+        assert_eq!(*sim.balls[1].pos(), (-0.5, 0.).into());
+    }
+
+    #[test]
+    fn escapes_in_per_ball_observables() {
+        // As in `hole`, the first ball escapes, after which the second, now at
+        // index 0, bounces around the wall at constant speed.
+        let escaping = || {
+            let mut sim = Simulation::new(1.);
+            sim.balls
+                .push(Ball::new((0., 0.3).into(), (1., 0.).into(), 0.1));
+            sim.balls
+                .push(Ball::new((0., -0.3).into(), (-0.5, 0.).into(), 0.1));
+            sim.initialise().unwrap();
+            sim.py_set_hole(0., 0.5).unwrap();
+            sim
+        };
+
+        assert_eq!(escaping().collision_pairs(2).unwrap(), vec![(0, None)]);
+        assert_eq!(
+            escaping().collision_type_ratio(2, 2).unwrap().1,
+            vec![0., 0.5]
+        );
+
+        let flights = escaping().free_flight_times(4, 1).unwrap();
+        assert_eq!(flights.counts, vec![2]);
+
+        let speeds = escaping().time_averaged_speeds(3).unwrap();
+        assert_eq!(speeds.len(), 1);
+        assert!((speeds[0] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn collision_errors() {
+        // Running methods report the same errors as `next_collision`, so a
+        // time limit is a `RuntimeError` rather than bad dynamics.
+        pyo3::prepare_freethreaded_python();
+        let mut sim = glancing();
+        let max_time = sim.global_time;
+        sim.set_max_time(Some(max_time));
+        let errors = [
+            sim.collision_pairs(5).unwrap_err(),
+            sim.free_flight_times(5, 2).err().unwrap(),
+            sim.py_run_until_time(1.).unwrap_err(),
+        ];
+        Python::with_gil(|py| {
+            for err in errors {
+                assert!(err.is_instance_of::<PyRuntimeError>(py), "{err}");
+            }
+        });
+    }
+}
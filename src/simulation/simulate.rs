@@ -1,4 +1,4 @@
-use crate::dynamics::ball::{Ball, Container};
+use crate::dynamics::ball::{Ball, Container, LineSegment};
 use crate::dynamics::collide::Collide;
 use crate::dynamics::maths::FloatVec;
 use crate::dynamics::DynamicsError;
@@ -6,44 +6,235 @@ use crate::simulation::data::{DataEvent, PostData, PreData};
 use crate::simulation::event::{CollisionEvent, CollisionPartner};
 use itertools::Itertools;
 use pyo3::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::Normal;
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
+use std::f64::consts::PI;
+use std::ops::{Deref, DerefMut};
 
 struct Params {
     delta: f64,
+    max_time: Option<f64>,
+    k_b: f64,
+    force_substep: f64,
+    harmonic_k: Option<f64>,
+    hole: Option<(f64, f64)>,
+    wall_restitution: f64,
+    max_queue_size: Option<usize>,
+    strict: bool,
+    time_scale: f64,
 }
 
-#[pyclass(subclass)]
-#[pyo3(name = "_Simulation")]
-pub struct Simulation {
-    #[pyo3(get)]
+/// How far apart the participants of a collision may be, in strict mode,
+/// before it is treated as mistimed, as a fraction of the distance between
+/// their centres at contact. `Ball::touching` is no use here: it allows an
+/// error of one unit in the last place, but the `delta` fudge factor stops
+/// every `Ball` a little short of contact, so it would flag every collision.
+/// The tolerance scales with the radii, so that the check is as strict for
+/// small `Ball`s as for large ones.
+const CONTACT_TOLERANCE: f64 = 1e-3;
+
+/// How close in time a collision may follow another sharing a participant
+/// before the two are counted as one simultaneous multi-ball contact.
+const SIMULTANEOUS_TOLERANCE: f64 = 1e-9;
+
+/// The collision counts of a pair of `Ball`s when their collision time was
+/// predicted, alongside the (absolute) time itself.
+type CachedPrediction = ((usize, usize), Option<f64>);
+
+/// A `Ball` as `(pos, vel, r, frozen, is_tracer, payload)`.
+type BallState = ((f64, f64), (f64, f64), f64, bool, bool, f64);
+/// A `CollisionEvent` as `(i, j, baffle, t, old_vels)`, where `j` is the
+/// partner `Ball`, `baffle` the partner baffle, and both are `None` for the
+/// container.
+type EventState = (
+    usize,
+    Option<usize>,
+    Option<usize>,
+    f64,
+    ((f64, f64), (f64, f64)),
+);
+/// Everything needed to resume a `Simulation` exactly: the global time, the
+/// collision count, the `Ball`s, the per-`Ball` collision counts and the
+/// collision queue.
+pub type SimulationState = (f64, usize, Vec<BallState>, Vec<usize>, Vec<EventState>);
+
+/// The state of a simulation and everything that runs it. It is held by the
+/// Python-facing `Simulation`, and can be moved onto another thread for a run
+/// in the background.
+pub struct SimulationCore {
     pub(crate) global_time: f64,
     params: Params,
     pub(crate) container: Container,
     pub(crate) balls: Vec<Ball>,
     pub(crate) collisions: BinaryHeap<Reverse<CollisionEvent>>,
+    pub(crate) collision_count: usize,
+    ball_collision_counts: Vec<usize>,
+    pub(crate) pair_cache: HashMap<(usize, usize), CachedPrediction>,
+    #[cfg(test)]
+    pub(crate) pair_calculations: usize,
+    force: Option<PyObject>,
+    pub(crate) escape_times: Vec<f64>,
+    pub(crate) baffles: Vec<LineSegment>,
+    pub(crate) initialised: bool,
+    pub(crate) simultaneous_collisions: usize,
 }
 
-impl Simulation {
-    pub fn new(radius: f64) -> Simulation {
+impl SimulationCore {
+    pub fn new(radius: f64) -> SimulationCore {
         let global_time = 0f64;
         let container = Container::new(radius);
         let balls = Vec::new();
         let collisions = BinaryHeap::new();
-        let params = Params { delta: 1e-6 };
-        Simulation {
+        let params = Params {
+            delta: 1e-6,
+            max_time: None,
+            k_b: 1.,
+            force_substep: 1e-3,
+            harmonic_k: None,
+            hole: None,
+            wall_restitution: 1.,
+            max_queue_size: None,
+            strict: false,
+            time_scale: 1.,
+        };
+        let collision_count = 0;
+        let ball_collision_counts = Vec::new();
+        let pair_cache = HashMap::new();
+        let force = None;
+        let escape_times = Vec::new();
+        let baffles = Vec::new();
+        let initialised = false;
+        let simultaneous_collisions = 0;
+        SimulationCore {
             global_time,
             params,
             container,
             balls,
             collisions,
+            collision_count,
+            ball_collision_counts,
+            pair_cache,
+            #[cfg(test)]
+            pair_calculations: 0,
+            force,
+            escape_times,
+            baffles,
+            initialised,
+            simultaneous_collisions,
+        }
+    }
+
+    pub fn thermalized(
+        radius: f64,
+        n: usize,
+        ball_radius: f64,
+        temperature: f64,
+        seed: u64,
+    ) -> Option<SimulationCore> {
+        // Build a `Simulation` filled with `n` non-overlapping `Ball`s placed
+        // uniformly at random, with velocities drawn from the Maxwell-Boltzmann
+        // distribution at `temperature` and the total momentum zeroed. The
+        // velocities are then rescaled so that the temperature is exact. Return
+        // `None` if the `Ball`s cannot be packed into the container.
+        let mut sim = SimulationCore::new(radius);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let max_attempts = 1_000 * n.max(1);
+        let reach = radius - ball_radius;
+        let mut attempts = 0;
+        while sim.balls.len() < n {
+            attempts += 1;
+            if attempts > max_attempts || reach <= 0. {
+                return None;
+            }
+            let pos = FloatVec::new(rng.gen_range(-reach..reach), rng.gen_range(-reach..reach));
+            let ball = Ball::new(pos, FloatVec::origin(), ball_radius);
+            if pos.magnitude() < reach && !sim.balls.iter().any(|other| ball.overlapping(other, 0.))
+            {
+                sim.balls.push(ball);
+            }
+        }
+
+        let normal = Normal::new(0., (sim.params.k_b * temperature).sqrt()).ok()?;
+        for ball in sim.balls.iter_mut() {
+            ball.set_vel(FloatVec::new(rng.sample(normal), rng.sample(normal)));
+        }
+        let com_velocity = sim.com_velocity();
+        for ball in sim.balls.iter_mut() {
+            ball.set_vel(ball.vel - com_velocity);
+        }
+        let current = sim.temperature();
+        if current > 0. {
+            let scale = (temperature / current).sqrt();
+            for ball in sim.balls.iter_mut() {
+                ball.set_vel(ball.vel * scale);
+            }
         }
+
+        sim.generate_collision_queue();
+        sim.generate_container_collisions();
+        sim.initialised = true;
+        Some(sim)
+    }
+
+    pub fn newtons_cradle(n: usize) -> Option<SimulationCore> {
+        // Build a line of `n` `Ball`s of radius 0.05 along the x-axis of a unit
+        // container, separated by small gaps. The leftmost `Ball` moves right
+        // with unit speed and the rest are at rest. Return `None` if `n` is
+        // zero or the line does not fit in the container.
+        let (r, spacing) = (0.05, 0.11);
+        let length = spacing * (n as f64 - 1.);
+        if n == 0 || length / 2. + r >= 1. {
+            return None;
+        }
+        let mut sim = SimulationCore::new(1.);
+        for k in 0..n {
+            let pos = FloatVec::new(-length / 2. + spacing * k as f64, 0.);
+            let vel = if k == 0 {
+                FloatVec::new(1., 0.)
+            } else {
+                FloatVec::origin()
+            };
+            sim.balls.push(Ball::new(pos, vel, r));
+        }
+
+        sim.generate_collision_queue();
+        sim.generate_container_collisions();
+        sim.initialised = true;
+        Some(sim)
+    }
+
+    pub fn head_on(speed: f64) -> SimulationCore {
+        // Build two `Ball`s of radius 0.1 on the x-axis of a unit container,
+        // each moving towards the other with `speed`.
+        let mut sim = SimulationCore::new(1.);
+        sim.balls.push(Ball::new(
+            FloatVec::new(-0.5, 0.),
+            FloatVec::new(speed, 0.),
+            0.1,
+        ));
+        sim.balls.push(Ball::new(
+            FloatVec::new(0.5, 0.),
+            FloatVec::new(-speed, 0.),
+            0.1,
+        ));
+
+        sim.generate_collision_queue();
+        sim.generate_container_collisions();
+        sim.initialised = true;
+        sim
     }
 
-    fn calculate_collision_event(&self, i: usize, j: usize) -> Option<CollisionEvent> {
+    fn calculate_collision_event(&mut self, i: usize, j: usize) -> Option<CollisionEvent> {
         // Given two `Ball`s of the simulation at indices `i` and `j` of `balls`,
         // calculate the `CollisionEvent` between them, or return `None` if no
         // collision exists.
+        #[cfg(test)]
+        {
+            self.pair_calculations += 1;
+        }
         let (p, q) = (&self.balls[i], &self.balls[j]);
         let time_to_collision_relative = p.time_to_collision(q)?;
         let t = self.global_time + time_to_collision_relative;
@@ -62,11 +253,99 @@ impl Simulation {
         Some(CollisionEvent::new(i, j, t, old_vels))
     }
 
+    fn calculate_baffle_collisions(&self, i: usize) -> Vec<CollisionEvent> {
+        // The collisions of the `Ball` at index `i` with each of the baffles.
+        let ball = &self.balls[i];
+        let old_vels = (ball.vel().to_owned(), FloatVec::origin());
+        self.baffles
+            .iter()
+            .enumerate()
+            .filter_map(|(k, baffle)| {
+                let t = self.global_time + ball.time_to_collision(baffle)?;
+                Some(CollisionEvent::new(
+                    i,
+                    CollisionPartner::Baffle(k),
+                    t,
+                    old_vels,
+                ))
+            })
+            .collect()
+    }
+
+    fn ball_collision_count(&self, i: usize) -> usize {
+        self.ball_collision_counts.get(i).copied().unwrap_or(0)
+    }
+
+    fn record_collision(&mut self, i: usize, j: CollisionPartner) {
+        // Count the collision, both globally and for each `Ball` involved, so
+        // that cached predictions involving those `Ball`s are invalidated.
+        self.collision_count += 1;
+        let n = self.balls.len();
+        if self.ball_collision_counts.len() < n {
+            self.ball_collision_counts.resize(n, 0);
+        }
+        self.ball_collision_counts[i] += 1;
+        if let CollisionPartner::Ball(j) = j {
+            self.ball_collision_counts[j] += 1;
+        }
+    }
+
+    fn cached_collision_event(&mut self, i: usize, j: usize) -> Option<CollisionEvent> {
+        // As `calculate_collision_event`, but reuse the collision time for the
+        // pair if it was calculated since either `Ball` last collided. This
+        // saves calculating the same collision from both sides when two `Ball`s
+        // collide with each other. The cache only lives for one step, see
+        // `push_step_collisions`.
+        let key = (i.min(j), i.max(j));
+        let counts = (
+            self.ball_collision_count(key.0),
+            self.ball_collision_count(key.1),
+        );
+        match self.pair_cache.get(&key) {
+            Some(&(cached_counts, t)) if cached_counts == counts => {
+                let old_vels = (self.balls[i].vel, self.balls[j].vel);
+                t.map(|t| CollisionEvent::new(i, CollisionPartner::Ball(j), t, old_vels))
+            }
+            _ => {
+                let collision_event = self.calculate_collision_event(i, j);
+                let t = collision_event.as_ref().map(|event| event.time());
+                self.pair_cache.insert(key, (counts, t));
+                collision_event
+            }
+        }
+    }
+
+    pub(crate) fn invalidate_queue(&mut self) {
+        // Drop every scheduled collision, e.g. because the `Ball`s have been
+        // changed by hand. The queue must be initialised again before running.
+        self.collisions.clear();
+        self.initialised = false;
+    }
+
+    pub(crate) fn reset_ball_counters(&mut self) {
+        // Forget everything recorded about individual `Ball`s, e.g. because
+        // they have all been replaced, so that nothing carries over to `Ball`s
+        // that happen to take the same indices.
+        self.ball_collision_counts.clear();
+        self.escape_times.clear();
+        self.simultaneous_collisions = 0;
+        self.pair_cache.clear();
+    }
+
+    fn rebuild_queue(&mut self) {
+        // Throw away every scheduled collision and predict them all afresh,
+        // e.g. because the velocities of all the `Ball`s have changed at once.
+        self.collisions.clear();
+        self.generate_collision_queue();
+        self.generate_container_collisions();
+    }
+
     pub(crate) fn generate_collision_queue(&mut self) {
         // Given a set of balls, calculate the order in which they will collide, assuming that
         // all their velocities remain constant. Store the collisions in a priority queue,
         // `self.collisions` so that the collisions can be efficiently looked up as the
-        // `Simulation` runs.
+        // `Simulation` runs. Any cached predictions may be stale, so they are dropped.
+        self.pair_cache.clear();
         let n = self.balls.len();
         for pair in (0..n).combinations(2) {
             let (i, j) = (pair[0], pair[1]);
@@ -79,14 +358,34 @@ impl Simulation {
 
     pub(crate) fn generate_container_collisions(&mut self) {
         // Given a set of balls within a container, calculate the collisions of the balls
-        // with the container, and push them in order to a collision queue.
+        // with the container, and any baffles inside it, and push them in order to a
+        // collision queue.
         let n = self.balls.len();
         for i in 0..n {
             if let Some(collision_event) = self.calculate_container_collision(i) {
                 let queue = &mut self.collisions;
                 queue.push(Reverse(collision_event));
             }
+            self.push_baffle_collisions(i);
+        }
+    }
+
+    fn push_baffle_collisions(&mut self, i: usize) {
+        for collision_event in self.calculate_baffle_collisions(i) {
+            self.collisions.push(Reverse(collision_event));
+        }
+    }
+
+    fn push_step_collisions(&mut self, i: usize, j: CollisionPartner) {
+        // Predict the next collisions of the `Ball`s that have just collided.
+        // A cached prediction can only be reused within the step, between the
+        // two `Ball`s, so the cache is emptied straight after rather than
+        // being left to grow.
+        self.push_collisions(i);
+        if let CollisionPartner::Ball(j) = j {
+            self.push_collisions(j);
         }
+        self.pair_cache.clear();
     }
 
     fn push_collisions(&mut self, i: usize) {
@@ -95,13 +394,13 @@ impl Simulation {
         // collision queue.
         let n = self.balls.len();
         for j in 0..i {
-            if let Some(collision_event) = self.calculate_collision_event(i, j) {
+            if let Some(collision_event) = self.cached_collision_event(i, j) {
                 self.collisions.push(Reverse(collision_event));
             }
         }
 
         for j in i + 1..n {
-            if let Some(collision_event) = self.calculate_collision_event(i, j) {
+            if let Some(collision_event) = self.cached_collision_event(i, j) {
                 self.collisions.push(Reverse(collision_event));
             }
         }
@@ -109,12 +408,62 @@ impl Simulation {
         if let Some(collision_event) = self.calculate_container_collision(i) {
             self.collisions.push(Reverse(collision_event));
         }
+        self.push_baffle_collisions(i);
+        self.prune_if_full();
+    }
+
+    fn prune_if_full(&mut self) {
+        // Stale events are only dropped lazily, as they are popped, so in long
+        // runs they pile up in the queue. If the queue has outgrown its limit,
+        // rebuild it from only the events that are still current.
+        match self.params.max_queue_size {
+            Some(max) if self.collisions.len() > max => {
+                let events = std::mem::take(&mut self.collisions).into_vec();
+                self.collisions = events
+                    .into_iter()
+                    .filter(|Reverse(collision_event)| self.is_current(collision_event))
+                    .collect();
+            }
+            _ => (),
+        }
     }
 
     pub fn step(&mut self, t: f64) {
-        // Move the simulation forward in time by `t` seconds.
-        for ball in self.balls.iter_mut() {
-            ball.step(t * (1. - self.params.delta))
+        // Move the simulation forward in time by `t` seconds. If there is a
+        // central harmonic force, the `Ball`s move along their ellipses instead
+        // of in straight lines. That bends the velocity of every moving `Ball`,
+        // which invalidates the straight-line prediction of every pair with a
+        // moving member, so the whole queue is rebuilt. This costs O(N^2)
+        // predictions on every step, rather than the O(N) of re-predicting the
+        // `Ball`s in a collision, and it happens on every force sub-step as
+        // well as on every collision. Predicting contacts on the ellipses
+        // themselves would avoid it, but is not done.
+        self.advance(t);
+        if self.params.harmonic_k.is_some() {
+            self.rebuild_queue();
+        }
+    }
+
+    fn advance(&mut self, t: f64) {
+        // Move the `Ball`s forward in time by `t` seconds, as in `step`, but
+        // leave the collision queue alone.
+        let t_fudged = t * (1. - self.params.delta);
+        match self.params.harmonic_k {
+            Some(k) => {
+                let omega = k.sqrt();
+                let (sin, cos) = (omega * t_fudged).sin_cos();
+                let centre = self.container.centre;
+                for ball in self.balls.iter_mut().filter(|ball| !ball.frozen) {
+                    let (offset, vel) = (ball.pos - centre, ball.vel);
+                    ball.pos = centre + offset * cos + vel * (sin / omega);
+                    ball.vel = vel * cos - offset * (omega * sin);
+                }
+            }
+            None => {
+                for ball in self.balls.iter_mut() {
+                    ball.step(t_fudged)
+                }
+            }
         }
         self.global_time += t;
     }
@@ -164,8 +513,17 @@ impl Simulation {
             CollisionPartner::Ball(j) => self.collide_by_index(i, j),
             CollisionPartner::Container => {
                 let p = &mut self.balls[i];
-                p.collide(&mut self.container)
+                p.collide(&mut self.container)?;
+                // The wall normal is radial for either wall of the container,
+                // so only that component of the velocity loses speed.
+                let e = self.params.wall_restitution;
+                if e != 1. {
+                    let normal = self.container.offset(p.pos).normalize();
+                    p.set_vel(p.vel.reject_from(&normal) + p.vel.project_onto(&normal) * e);
+                }
+                Ok(())
             }
+            CollisionPartner::Baffle(k) => self.balls[i].collide(&mut self.baffles[k]),
         }
     }
 
@@ -173,65 +531,398 @@ impl Simulation {
         // Pop the next collision from the queue. If it is still valid, i.e.
         // if the velocities of the involved `Ball`s have not changed, return
         // the `CollisionEvent`. Otherwise, return `None`.
-        let reverse_collision = self.collisions.pop()?;
-        let collision_info = reverse_collision.0;
-        let (i, j, t, old_vels) = collision_info.into();
+        let Reverse(collision_event) = self.collisions.pop()?;
+        self.is_current(&collision_event).then_some(collision_event)
+    }
+
+    fn is_current(&self, collision_event: &CollisionEvent) -> bool {
+        // Whether the velocities of the `Ball`s involved in `collision_event`
+        // are unchanged since it was predicted, i.e. whether it will happen.
+        let (i, j) = collision_event.participants();
         let p = &self.balls[i];
         let q_vel = match j {
             CollisionPartner::Ball(j) => self.balls[j].vel,
-            CollisionPartner::Container => FloatVec::origin(),
+            CollisionPartner::Container | CollisionPartner::Baffle(_) => FloatVec::origin(),
         }; // Just comparing 0f == 0f?
-        let curr_vels = (p.vel, q_vel);
-        if curr_vels == old_vels {
-            Some(CollisionEvent::new(i, j, t, old_vels))
-        } else {
-            None
+        (p.vel, q_vel) == collision_event.old_vels()
+    }
+
+    pub fn ball_next_collision(&self, index: usize) -> Option<(f64, CollisionPartner)> {
+        // Find the soonest valid event in the queue involving the `Ball` at
+        // `index`, and return its time and the other participant, as seen
+        // from the `Ball` at `index`.
+        self.collisions
+            .iter()
+            .map(|Reverse(collision_event)| collision_event)
+            .filter(|collision_event| self.is_current(collision_event))
+            .filter_map(|collision_event| {
+                let partner = match collision_event.participants() {
+                    (i, partner) if i == index => partner,
+                    (i, CollisionPartner::Ball(j)) if j == index => CollisionPartner::Ball(i),
+                    _ => return None,
+                };
+                Some((collision_event.time(), partner))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    fn peek_collision(&mut self) -> Option<CollisionEvent> {
+        // The next valid collision in the queue, left in place. Stale events
+        // at the front of the queue are dropped along the way, as
+        // `next_collision` would.
+        while let Some(Reverse(collision_event)) = self.collisions.peek() {
+            if self.is_current(collision_event) {
+                break;
+            }
+            self.collisions.pop();
+        }
+        self.collisions
+            .peek()
+            .map(|Reverse(collision_event)| collision_event.clone())
+    }
+
+    fn upcoming_collision_time(&mut self) -> Result<f64, DynamicsError> {
+        // As `next_collision_time`, but infinite if no collision is scheduled
+        // at all, e.g. because every `Ball` is at rest.
+        if self.peek_collision().is_none() {
+            return Ok(f64::INFINITY);
         }
+        self.next_collision_time()
     }
 
     fn next_collision_or_err(&mut self) -> Result<CollisionEvent, DynamicsError> {
         let mut collision_event = None;
-        while collision_event.is_none() {
+        while collision_event.is_none() && !self.collisions.is_empty() {
             collision_event = self.next_collision();
         }
 
-        collision_event.ok_or(DynamicsError::SimulationFailure)
+        let collision_event = collision_event.ok_or(DynamicsError::SimulationFailure)?;
+        match self.params.max_time {
+            Some(max_time) if collision_event.time() > max_time => {
+                // Put the collision back so that the `Simulation` is left in a
+                // consistent state and can be continued if the limit is raised.
+                self.collisions.push(Reverse(collision_event));
+                Err(DynamicsError::TimeLimitExceeded)
+            }
+            _ => Ok(collision_event),
+        }
+    }
+
+    fn apply_force(&mut self, dt: f64) -> Result<(), DynamicsError> {
+        // Kick the velocity of every (unfrozen) `Ball` by the impulse imparted
+        // by the force field over the time `dt`. All `Ball`s have unit mass.
+        let Some(force) = &self.force else {
+            return Ok(());
+        };
+        Python::with_gil(|py| {
+            for ball in self.balls.iter_mut().filter(|ball| !ball.frozen) {
+                let (fx, fy): (f64, f64) = force
+                    .call1(py, (ball.pos.x, ball.pos.y))
+                    .and_then(|f| f.extract(py))
+                    .map_err(|_| DynamicsError::ForceFieldFailure)?;
+                ball.vel += FloatVec::new(fx, fy) * dt;
+            }
+            Ok(())
+        })
+    }
+
+    fn integrate_force(&mut self, horizon: f64) -> Result<(), DynamicsError> {
+        // If a force field is set, the motion between collisions is no longer
+        // ballistic. Integrate it in fixed sub-steps, re-calculating the
+        // collision queue after every kick, until the next collision is due
+        // within a single sub-step, or the next sub-step would pass `horizon`.
+        // The collision itself is then resolved as usual. This is approximate:
+        // collisions are only predicted correctly to within the curvature of
+        // the trajectory over one sub-step. A central harmonic force is handled
+        // in the same way, except the motion over each sub-step is exact. If
+        // no collision is scheduled, e.g. because the `Ball`s start at rest,
+        // the force may still set them moving, so the sub-steps carry on.
+        if self.force.is_none() && self.params.harmonic_k.is_none() {
+            return Ok(());
+        }
+        let substep = self.params.force_substep;
+        while self.global_time + substep <= horizon {
+            let next = self.upcoming_collision_time()?;
+            if next <= self.global_time + substep {
+                break;
+            }
+            // Either the force or the harmonic motion has bent the
+            // trajectories, so rebuild the queue once for both.
+            self.advance(substep);
+            self.apply_force(substep)?;
+            self.rebuild_queue();
+            // Any moving `Ball` will meet a wall, so if there is still nothing
+            // scheduled, every `Ball` is at rest where the force vanishes, and
+            // an unbounded run would never end.
+            if next.is_infinite()
+                && horizon.is_infinite()
+                && self.upcoming_collision_time()?.is_infinite()
+            {
+                return Err(DynamicsError::SimulationFailure);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_finite(&self, i: usize, j: CollisionPartner) -> Result<(), DynamicsError> {
+        // Make sure that a collision has not corrupted the state of the `Ball`s
+        // involved with non-finite positions or velocities, which would
+        // otherwise silently propagate through the rest of the `Simulation`.
+        let mut indices = vec![i];
+        if let CollisionPartner::Ball(j) = j {
+            indices.push(j);
+        }
+        for k in indices {
+            let ball = &self.balls[k];
+            if !(ball.pos.is_finite() && ball.vel.is_finite()) {
+                return Err(DynamicsError::NonFiniteState {
+                    collision: self.collision_count,
+                    time: self.global_time,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_contact(&self, i: usize, j: CollisionPartner) -> Result<(), DynamicsError> {
+        // In strict mode, make sure that the participants of a collision are
+        // actually touching before it is resolved, to catch events that drift
+        // early or late. The `delta` fudge factor always leaves a small gap, so
+        // only gaps beyond `CONTACT_TOLERANCE` are treated as errors.
+        if !self.params.strict {
+            return Ok(());
+        }
+        let gap = self.contact_gap(i, j);
+        if gap > CONTACT_TOLERANCE * self.contact_distance(i, j) {
+            return Err(DynamicsError::MistimedCollision {
+                collision: self.collision_count,
+                gap,
+            });
+        }
+        Ok(())
+    }
+
+    fn in_hole(&self, i: usize, j: CollisionPartner) -> bool {
+        // Whether a collision of `Ball` `i` with partner `j` is actually the
+        // `Ball` reaching the hole in the outer wall of the container.
+        let (centre, half_width) = match (self.params.hole, j) {
+            (Some(hole), CollisionPartner::Container) => hole,
+            _ => return false,
+        };
+        let ball = &self.balls[i];
+        let offset = self.container.offset(ball.pos);
+        let distance = offset.magnitude();
+        if let Some(inner) = self.container.inner_radius {
+            let outer_gap = (self.container.r - ball.r - distance).abs();
+            let inner_gap = (distance - ball.r - inner).abs();
+            if inner_gap < outer_gap {
+                return false;
+            }
+        }
+        let angle = offset.y.atan2(offset.x) - centre;
+        let angle = (angle + PI).rem_euclid(2. * PI) - PI;
+        angle.abs() <= half_width
+    }
+
+    fn escape(&mut self, i: usize) {
+        // Remove `Ball` `i`, which has left through the hole, and record when.
+        // The indices of the later `Ball`s shift down, so the queue is rebuilt.
+        self.balls.remove(i);
+        if i < self.ball_collision_counts.len() {
+            self.ball_collision_counts.remove(i);
+        }
+        self.escape_times.push(self.global_time);
+        self.rebuild_queue();
+    }
+
+    pub(crate) fn ensure_initialised(&self) -> Result<(), DynamicsError> {
+        // Everything that runs the `Simulation` goes through here first. The
+        // queue is only meaningful once it has been built by `initialise`, and
+        // is dropped again when the `Ball`s are changed by hand.
+        if self.initialised {
+            Ok(())
+        } else {
+            Err(DynamicsError::Uninitialised)
+        }
     }
 
     pub(crate) fn step_through_collision(&mut self) -> Result<(), DynamicsError> {
         // Run the simulation to and including the next collision that is scheduled
         // to occur. Calculate the dynamics of the collision and update the
         // collisions queue accordingly.
+        self.ensure_initialised()?;
+        self.integrate_force(f64::INFINITY)?;
         let next_collision = self.next_collision_or_err()?;
         let (i, j, t, _) = next_collision.into();
         self.step_until(t)?;
-        self.collide_members(i, j)?;
-        self.push_collisions(i);
-        if let CollisionPartner::Ball(j) = j {
-            self.push_collisions(j);
+        if self.in_hole(i, j) {
+            self.escape(i);
+            return Ok(());
         }
+        self.check_contact(i, j)?;
+        self.collide_members(i, j)?;
+        self.check_finite(i, j)?;
+        self.record_collision(i, j);
+        self.push_step_collisions(i, j);
+        self.detect_simultaneous(i, j);
         Ok(())
     }
 
+    fn detect_simultaneous(&mut self, i: usize, j: CollisionPartner) {
+        // Having just resolved a collision between `i` and `j`, check whether
+        // the next one involves either of them at the same moment, as when a
+        // ball strikes a row of touching balls. Such multi-ball contacts are
+        // resolved a pair at a time, in an order set by tie-breaking, so they
+        // are counted in `simultaneous_collisions`.
+        let Some(collision_event) = self.peek_collision() else {
+            return;
+        };
+        let involved = |k: usize| k == i || j.index() == Some(k);
+        let (k, l) = collision_event.participants();
+        if collision_event.time() - self.global_time <= SIMULTANEOUS_TOLERANCE
+            && (involved(k) || l.index().is_some_and(involved))
+        {
+            self.simultaneous_collisions += 1;
+        }
+    }
+
     /// Run the simulation through the next collision, as above, but publish
     /// the data associated with the collision as a `DataEvent` that can be
     /// streamed.
-    fn step_with_data(&mut self) -> Result<DataEvent, DynamicsError> {
+    pub(crate) fn step_with_data(&mut self) -> Result<DataEvent, DynamicsError> {
+        self.ensure_initialised()?;
+        self.integrate_force(f64::INFINITY)?;
         let next_collision = self.next_collision_or_err()?;
         let (i, j, t, _) = next_collision.into();
         self.step_until(t)?;
+        if self.in_hole(i, j) {
+            let event = DataEvent::Escape {
+                time: self.global_time,
+                index: i,
+                pre: self.balls[i].clone(),
+            };
+            self.escape(i);
+            return Ok(event);
+        }
+        self.check_contact(i, j)?;
         let pre_data = PreData::from_indices(self, i, j);
         self.collide_members(i, j)?;
+        self.check_finite(i, j)?;
+        self.record_collision(i, j);
         let post_data = PostData::from_indices(self, i, j);
 
-        self.push_collisions(i);
-        if let CollisionPartner::Ball(j) = j {
-            self.push_collisions(j);
-        }
+        self.push_step_collisions(i, j);
+        self.detect_simultaneous(i, j);
 
         Ok((pre_data, post_data).into())
     }
 
+    fn contact_gap(&self, i: usize, j: CollisionPartner) -> f64 {
+        // How far `Ball` `i` is from touching its partner `j`. For an annular
+        // container, the nearer of the two walls is used, and for a baffle,
+        // the nearest point along it.
+        let ball = &self.balls[i];
+        match j {
+            CollisionPartner::Ball(j) => {
+                let other = &self.balls[j];
+                ((ball.pos - other.pos).magnitude() - ball.r - other.r).abs()
+            }
+            CollisionPartner::Container => {
+                let distance = self.container.offset(ball.pos).magnitude();
+                let outer = (self.container.r - ball.r - distance).abs();
+                match self.container.inner_radius {
+                    Some(inner) => outer.min((distance - ball.r - inner).abs()),
+                    None => outer,
+                }
+            }
+            CollisionPartner::Baffle(k) => {
+                let closest = self.baffles[k].closest_point(ball.pos);
+                ((ball.pos - closest).magnitude() - ball.r).abs()
+            }
+        }
+    }
+
+    fn contact_distance(&self, i: usize, j: CollisionPartner) -> f64 {
+        // How far the centre of `Ball` `i` is from its partner `j` when they
+        // touch: the sum of the radii for another `Ball`, and the radius of
+        // `i` alone for a wall.
+        match j {
+            CollisionPartner::Ball(j) => self.balls[i].r + self.balls[j].r,
+            CollisionPartner::Container | CollisionPartner::Baffle(_) => self.balls[i].r,
+        }
+    }
+
+    fn nearest_wall(&self, i: usize) -> CollisionPartner {
+        // The container or baffle that `Ball` `i` is closest to touching, for
+        // working out which one a recorded collision was with.
+        (0..self.baffles.len()).map(CollisionPartner::Baffle).fold(
+            CollisionPartner::Container,
+            |nearest, baffle| {
+                if self.contact_gap(i, baffle) < self.contact_gap(i, nearest) {
+                    baffle
+                } else {
+                    nearest
+                }
+            },
+        )
+    }
+
+    pub fn replay(
+        &mut self,
+        events: &[(f64, usize, Option<usize>)],
+        tolerance: f64,
+    ) -> Result<(), DynamicsError> {
+        // Apply a recorded sequence of collisions, `(time, i, j)` with `j` as
+        // `None` for the container or a baffle, by stepping to each time and
+        // forcing the collision, bypassing the queue. If an event refers to a
+        // `Ball` that does not exist, or its participants are not within
+        // `tolerance` of touching, an error identifies it, and the
+        // `Simulation` is returned to where it was before the replay.
+        // Otherwise the queue is rebuilt so that the `Simulation` can carry on
+        // from the end of the replay.
+        let saved = self.save_state();
+        self.collisions.clear();
+        match self.replay_events(events, tolerance) {
+            Ok(()) => {
+                self.rebuild_queue();
+                Ok(())
+            }
+            Err(e) => {
+                if self.load_state(saved).is_err() {
+                    self.invalidate_queue();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn replay_events(
+        &mut self,
+        events: &[(f64, usize, Option<usize>)],
+        tolerance: f64,
+    ) -> Result<(), DynamicsError> {
+        let n = self.balls.len();
+        for (event, &(t, i, j)) in events.iter().enumerate() {
+            let j = match j {
+                Some(j) if i < n && j < n && i != j => CollisionPartner::Ball(j),
+                None if i < n => CollisionPartner::Container,
+                _ => return Err(DynamicsError::ReplayMismatch { event }),
+            };
+            self.step_until(t)?;
+            let j = match j {
+                CollisionPartner::Container => self.nearest_wall(i),
+                j => j,
+            };
+            if self.contact_gap(i, j) > tolerance {
+                return Err(DynamicsError::ReplayMismatch { event });
+            }
+            self.collide_members(i, j)?;
+            self.check_finite(i, j)?;
+            self.record_collision(i, j);
+        }
+        Ok(())
+    }
+
     pub fn run_collisions(&mut self, n: usize) -> Result<(), DynamicsError> {
         // Run the `Simulation` through `n` collisions.
         for _ in 0..n {
@@ -241,12 +932,412 @@ impl Simulation {
         Ok(())
     }
 
+    pub fn run_collisions_counted(&mut self, n: usize) -> Result<usize, (usize, DynamicsError)> {
+        // Run the `Simulation` through `n` collisions, returning how many were
+        // completed. If a collision fails, the error is returned alongside the
+        // number completed before it, so that long runs can be diagnosed.
+        for completed in 0..n {
+            self.step_through_collision()
+                .map_err(|err| (completed, err))?;
+        }
+
+        Ok(n)
+    }
+
+    pub(crate) fn next_collision_time(&mut self) -> Result<f64, DynamicsError> {
+        // Find the time of the next valid collision in the queue, without
+        // running the simulation through it.
+        self.ensure_initialised()?;
+        let collision_event = self.next_collision_or_err()?;
+        let t = collision_event.time();
+        self.collisions.push(Reverse(collision_event));
+        Ok(t)
+    }
+
+    pub fn run_until_time(&mut self, t: f64) -> Result<usize, DynamicsError> {
+        // Run the `Simulation` through every collision scheduled before time
+        // `t`, then step forward to exactly `t`. Return the number of
+        // collisions with the container that took place along the way. If
+        // nothing is scheduled to collide, the `Simulation` steps straight to
+        // `t`.
+        self.ensure_initialised()?;
+        let mut container_collisions = 0;
+        loop {
+            self.integrate_force(t)?;
+            if self.upcoming_collision_time()? > t {
+                break;
+            }
+            if let DataEvent::ContainerCollision { .. } = self.step_with_data()? {
+                container_collisions += 1;
+            }
+        }
+        self.step_until(t)?;
+
+        Ok(container_collisions)
+    }
+
+    pub(crate) fn observed_balls(&self, include_tracers: bool) -> impl Iterator<Item = &Ball> {
+        // The `Ball`s that contribute to statistics. Tracers still collide as
+        // usual, but can be left out of the observables.
+        self.balls
+            .iter()
+            .filter(move |ball| include_tracers || !ball.is_tracer)
+    }
+
+    pub fn payloads(&self) -> Vec<f64> {
+        // The scalar carried by each `Ball`, which plays no part in the
+        // dynamics, e.g. for colouring `Ball`s in a visualisation.
+        self.balls.iter().map(|ball| ball.payload).collect()
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        self.kinetic_energy_of(true)
+    }
+
+    pub fn kinetic_energy_of(&self, include_tracers: bool) -> f64 {
+        // The total kinetic energy of the `Ball`s, all of which have unit mass.
+        self.observed_balls(include_tracers)
+            .map(|ball| 0.5 * ball.vel.dot(&ball.vel))
+            .sum()
+    }
+
+    pub fn energy_components(&self) -> (f64, f64) {
+        self.energy_components_of(true)
+    }
+
+    pub fn energy_components_of(&self, include_tracers: bool) -> (f64, f64) {
+        // The kinetic energy of the `Ball`s split into the parts due to motion
+        // along x and along y, which should be equal at equilibrium.
+        self.observed_balls(include_tracers)
+            .fold((0., 0.), |(ex, ey), ball| {
+                (
+                    ex + 0.5 * ball.vel.x * ball.vel.x,
+                    ey + 0.5 * ball.vel.y * ball.vel.y,
+                )
+            })
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.temperature_of(true)
+    }
+
+    pub fn temperature_of(&self, include_tracers: bool) -> f64 {
+        // The temperature of the `Simulation`, from equipartition over the two
+        // translational degrees of freedom of each `Ball`.
+        let n = self.observed_balls(include_tracers).count();
+        if n == 0 {
+            0.
+        } else {
+            self.kinetic_energy_of(include_tracers) / (n as f64 * self.params.k_b)
+        }
+    }
+
+    pub fn mean_speed(&self) -> f64 {
+        self.mean_speed_of(true)
+    }
+
+    pub fn mean_speed_of(&self, include_tracers: bool) -> f64 {
+        // The average of `|v|` over the `Ball`s, or zero if there are none.
+        let n = self.observed_balls(include_tracers).count();
+        if n == 0 {
+            return 0.;
+        }
+        let total: f64 = self
+            .observed_balls(include_tracers)
+            .map(|ball| ball.vel.magnitude())
+            .sum();
+        total / n as f64
+    }
+
+    pub fn rms_speed(&self) -> f64 {
+        self.rms_speed_of(true)
+    }
+
+    pub fn rms_speed_of(&self, include_tracers: bool) -> f64 {
+        // The root-mean-square speed of the `Ball`s, `sqrt(<v^2>)`, or zero if
+        // there are none.
+        let n = self.observed_balls(include_tracers).count();
+        if n == 0 {
+            return 0.;
+        }
+        (2. * self.kinetic_energy_of(include_tracers) / n as f64).sqrt()
+    }
+
+    pub fn ideal_pressure(&self) -> f64 {
+        self.ideal_pressure_of(true)
+    }
+
+    pub fn ideal_pressure_of(&self, include_tracers: bool) -> f64 {
+        // The pressure predicted by the ideal gas law, `P = N k_B T / A`, for
+        // the current state of the `Simulation`.
+        let n = self.observed_balls(include_tracers).count() as f64;
+        n * self.params.k_b * self.temperature_of(include_tracers) / self.container.area()
+    }
+
+    pub(crate) fn outside_container(&self, ball: &Ball) -> bool {
+        // Whether `ball` pokes through a wall of the container, including the
+        // inner wall of an annulus.
+        let distance = self.container.offset(ball.pos).magnitude();
+        let inner = self.container.inner_radius.unwrap_or(f64::NEG_INFINITY);
+        distance + ball.r > self.container.r || distance - ball.r < inner
+    }
+
+    fn mobile_balls(&self) -> impl Iterator<Item = &Ball> {
+        self.balls.iter().filter(|ball| !ball.frozen)
+    }
+
+    pub fn centre_of_mass(&self) -> FloatVec {
+        // The mean position of the unfrozen `Ball`s, all of which have unit
+        // mass. Frozen `Ball`s have infinite mass, so they are excluded.
+        let n = self.mobile_balls().count();
+        if n == 0 {
+            return FloatVec::origin();
+        }
+        let sum = self
+            .mobile_balls()
+            .fold(FloatVec::origin(), |acc, ball| acc + ball.pos);
+        sum / n as f64
+    }
+
+    pub fn com_velocity(&self) -> FloatVec {
+        // The mean velocity of the unfrozen `Ball`s.
+        let n = self.mobile_balls().count();
+        if n == 0 {
+            return FloatVec::origin();
+        }
+        self.total_momentum() / n as f64
+    }
+
+    pub fn total_momentum(&self) -> FloatVec {
+        // The total momentum of the unfrozen `Ball`s, all of which have unit
+        // mass.
+        self.mobile_balls()
+            .fold(FloatVec::origin(), |acc, ball| acc + ball.vel)
+    }
+
+    pub fn force_substep(&self) -> f64 {
+        self.params.force_substep
+    }
+
+    pub fn set_force_substep(&mut self, substep: f64) {
+        // Change the length of the sub-steps that forces are integrated in,
+        // leaving the force itself as it is.
+        self.params.force_substep = substep;
+    }
+
+    pub fn set_force(&mut self, force: Option<PyObject>, substep: f64) {
+        // Set the force field acting on the `Ball`s, integrated in sub-steps of
+        // length `substep`. `None` returns to exact, event-driven dynamics.
+        self.force = force;
+        self.params.force_substep = substep;
+        self.rebuild_queue();
+    }
+
+    pub fn run_until_wall(&mut self) -> Result<usize, DynamicsError> {
+        // Run the `Simulation` until the next collision of a `Ball` with the
+        // container has been resolved, and return the index of that `Ball`.
+        loop {
+            if let DataEvent::ContainerCollision { index, .. } = self.step_with_data()? {
+                return Ok(index);
+            }
+        }
+    }
+
+    pub fn delta(&self) -> f64 {
+        self.params.delta
+    }
+
+    pub fn set_delta(&mut self, delta: f64) {
+        // Set the fraction by which every step falls short of the collision it
+        // steps to, which stops rounding from leaving `Ball`s overlapping.
+        self.params.delta = delta;
+    }
+
+    pub fn k_b(&self) -> f64 {
+        self.params.k_b
+    }
+
+    pub fn set_k_b(&mut self, k_b: f64) {
+        // Set the Boltzmann constant used for the temperature and related
+        // observables, e.g. to work in SI rather than reduced units.
+        self.params.k_b = k_b;
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.params.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        // Set the length of one unit of simulation time in the units that
+        // times are reported in, e.g. seconds. The dynamics are unaffected.
+        self.params.time_scale = time_scale;
+    }
+
+    pub fn reported_time(&self, t: f64) -> f64 {
+        // Convert a time or interval `t` in simulation units into the units
+        // set by `set_time_scale`.
+        t * self.params.time_scale
+    }
+
+    pub fn harmonic_k(&self) -> Option<f64> {
+        self.params.harmonic_k
+    }
+
+    pub fn set_central_harmonic(&mut self, k: Option<f64>) {
+        // Attract every `Ball` towards the centre of the container with a force
+        // `F = -k r`. `None` returns to ballistic motion between collisions.
+        self.params.harmonic_k = k;
+        self.rebuild_queue();
+    }
+
+    pub fn set_hole(&mut self, hole: Option<(f64, f64)>) {
+        // Open a hole in the outer wall of the container, spanning `half_width`
+        // radians either side of the angle `centre`, as `(centre, half_width)`.
+        // `Ball`s reaching it escape rather than bouncing. `None` closes it.
+        self.params.hole = hole;
+    }
+
+    pub fn max_queue_size(&self) -> Option<usize> {
+        self.params.max_queue_size
+    }
+
+    pub fn set_max_queue_size(&mut self, max_queue_size: Option<usize>) {
+        // Prune stale events from the collision queue whenever it grows beyond
+        // `max_queue_size`. `None` never prunes.
+        self.params.max_queue_size = max_queue_size;
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        // Check that the participants of every collision are touching before
+        // resolving it. Off by default, as it costs a little on every step.
+        self.params.strict = strict;
+    }
+
+    pub fn wall_restitution(&self) -> f64 {
+        self.params.wall_restitution
+    }
+
+    pub fn set_wall_restitution(&mut self, e: f64) {
+        // Scale the normal component of the velocity of a `Ball` by `e` every
+        // time it bounces off the container. Below one the walls are lossy,
+        // so the gas cools over time.
+        self.params.wall_restitution = e;
+    }
+
+    pub fn save_state(&self) -> SimulationState {
+        // Capture the dynamic state of the `Simulation`, including the contents
+        // of the collision queue, so that it can be resumed exactly. The heap is
+        // stored in its internal order, which `load_state` preserves, so that
+        // even simultaneous events are popped in the same order.
+        let balls = self
+            .balls
+            .iter()
+            .map(|ball| {
+                (
+                    (ball.pos.x, ball.pos.y),
+                    (ball.vel.x, ball.vel.y),
+                    ball.r,
+                    ball.frozen,
+                    ball.is_tracer,
+                    ball.payload,
+                )
+            })
+            .collect();
+        let queue = self
+            .collisions
+            .iter()
+            .map(|Reverse(event)| {
+                let (i, j, t, (p, q)) = event.clone().into();
+                let baffle = match j {
+                    CollisionPartner::Baffle(k) => Some(k),
+                    _ => None,
+                };
+                (i, j.index(), baffle, t, ((p.x, p.y), (q.x, q.y)))
+            })
+            .collect();
+        (
+            self.global_time,
+            self.collision_count,
+            balls,
+            self.ball_collision_counts.clone(),
+            queue,
+        )
+    }
+
+    pub fn load_state(&mut self, state: SimulationState) -> Result<(), DynamicsError> {
+        // Restore a state captured by `save_state`, rebuilding the collision
+        // queue directly rather than re-predicting it, which would not be
+        // bit-identical because of the `delta` fudge factor. The container and
+        // parameters are left as they are. If the state refers to `Ball`s that
+        // it does not contain, or to baffles that are missing, nothing is
+        // changed.
+        let (global_time, collision_count, balls, ball_collision_counts, queue) = state;
+        let n = balls.len();
+        let in_range = ball_collision_counts.len() <= n
+            && queue.iter().all(|&(i, j, baffle, _, _)| {
+                i < n
+                    && j.is_none_or(|j| j < n)
+                    && baffle.is_none_or(|k| j.is_none() && k < self.baffles.len())
+            });
+        if !in_range {
+            return Err(DynamicsError::SimulationFailure);
+        }
+
+        self.global_time = global_time;
+        self.collision_count = collision_count;
+        self.balls = balls
+            .into_iter()
+            .map(|(pos, vel, r, frozen, is_tracer, payload)| {
+                let mut ball = Ball::new(pos.into(), vel.into(), r);
+                ball.frozen = frozen;
+                ball.is_tracer = is_tracer;
+                ball.payload = payload;
+                ball
+            })
+            .collect();
+        self.ball_collision_counts = ball_collision_counts;
+        let events: Vec<_> = queue
+            .into_iter()
+            .map(|(i, j, baffle, t, (p, q))| {
+                let j = match (j, baffle) {
+                    (Some(j), _) => CollisionPartner::Ball(j),
+                    (None, Some(k)) => CollisionPartner::Baffle(k),
+                    (None, None) => CollisionPartner::Container,
+                };
+                Reverse(CollisionEvent::new(i, j, t, (p.into(), q.into())))
+            })
+            .collect();
+        self.collisions = BinaryHeap::from(events);
+        self.initialised = true;
+        self.pair_cache.clear();
+        Ok(())
+    }
+
+    pub fn max_time(&self) -> Option<f64> {
+        self.params.max_time
+    }
+
+    pub fn set_max_time(&mut self, max_time: Option<f64>) {
+        // Set the time beyond which the `Simulation` refuses to run. `None`
+        // removes the limit.
+        self.params.max_time = max_time;
+    }
+
     pub fn iter_pressure<'a>(&'a mut self) -> SimulationPressures<'a> {
-        SimulationPressures { parent: self }
+        self.iter_pressure_of(true)
+    }
+
+    pub fn iter_pressure_of<'a>(&'a mut self, include_tracers: bool) -> SimulationPressures<'a> {
+        // The impulses delivered to the container, skipping those of tracer
+        // `Ball`s unless `include_tracers` is set.
+        SimulationPressures {
+            parent: self,
+            include_tracers,
+        }
     }
 }
 
-impl Iterator for Simulation {
+impl Iterator for SimulationCore {
     type Item = DataEvent;
     fn next(&mut self) -> Option<Self::Item> {
         // We want to panic rather than convert the `Err` into a `None` because
@@ -256,15 +1347,74 @@ impl Iterator for Simulation {
     }
 }
 
+/// The simulation as seen from Python. Its state lives in a `SimulationCore`,
+/// which a run started by `run_async` takes away until it is joined, so every
+/// method checks that the state is there, with `check_idle`, before using it.
+#[pyclass(subclass)]
+#[pyo3(name = "_Simulation")]
+pub struct Simulation {
+    pub(crate) core: Option<SimulationCore>,
+}
+
+impl Simulation {
+    pub fn new(radius: f64) -> Simulation {
+        SimulationCore::new(radius).into()
+    }
+
+    pub fn thermalized(
+        radius: f64,
+        n: usize,
+        ball_radius: f64,
+        temperature: f64,
+        seed: u64,
+    ) -> Option<Simulation> {
+        SimulationCore::thermalized(radius, n, ball_radius, temperature, seed).map(Simulation::from)
+    }
+
+    pub fn newtons_cradle(n: usize) -> Option<Simulation> {
+        SimulationCore::newtons_cradle(n).map(Simulation::from)
+    }
+
+    pub fn head_on(speed: f64) -> Simulation {
+        SimulationCore::head_on(speed).into()
+    }
+}
+
+impl From<SimulationCore> for Simulation {
+    fn from(core: SimulationCore) -> Self {
+        Simulation { core: Some(core) }
+    }
+}
+
+impl Deref for Simulation {
+    type Target = SimulationCore;
+    fn deref(&self) -> &SimulationCore {
+        // Methods check that the state is here before touching it, so finding
+        // it missing is a bug rather than something to report.
+        self.core
+            .as_ref()
+            .expect("the simulation is running in the background")
+    }
+}
+
+impl DerefMut for Simulation {
+    fn deref_mut(&mut self) -> &mut SimulationCore {
+        self.core
+            .as_mut()
+            .expect("the simulation is running in the background")
+    }
+}
+
 pub struct SimulationPressures<'a> {
-    parent: &'a mut Simulation,
+    parent: &'a mut SimulationCore,
+    include_tracers: bool,
 }
 
 impl<'a> Iterator for SimulationPressures<'a> {
     type Item = (f64, f64); // that is, (time, delta_p)
     fn next(&mut self) -> Option<Self::Item> {
-        for event in self.parent.into_iter() {
-            match event.container_pressure() {
+        for event in &mut *self.parent {
+            match event.container_pressure_of(self.include_tracers) {
                 Some(delta_p) => return Some((event.time(), delta_p)),
                 None => continue,
             };
@@ -1,3 +1,4 @@
+from eight_ball import _Ball, _Simulation, PartnerKind
 from eight_ball.simulation import floor_sqrt
 
 def test_floor_sqrt():
@@ -7,3 +8,33 @@ def test_floor_sqrt():
     assert floor_sqrt(16) == 4
     assert floor_sqrt(17) == 4
     assert floor_sqrt(6) == 2
+
+
+def test_histogram_result():
+    sim = _Simulation(1.)
+    sim.add_balls([_Ball(pos=(-0.5, 0.), vel=(1., 0.), r=0.1),
+                   _Ball(pos=(0.5, 0.1), vel=(-1., 0.), r=0.1)])
+    sim.initialise()
+    hist = sim.collision_times(100, 0., 2., 10)
+    assert len(hist.centres) == 10
+    assert len(hist.edges) == 11
+    assert len(hist.counts) == 10
+    assert abs(hist.width - 0.2) < 1e-12
+    assert abs(sum(hist.density()) * hist.width - 1.) < 1e-12
+
+
+def test_partner_kind():
+    sim = _Simulation(1.)
+    sim.add_balls([_Ball(pos=(-0.5, 0.), vel=(1., 0.), r=0.1),
+                   _Ball(pos=(0.5, 0.1), vel=(-1., 0.), r=0.1)])
+    sim.initialise()
+    kinds = []
+    for event in sim.collision_events(4):
+        if event["partner"] == PartnerKind.Ball:
+            assert event["j"] == 1 - event["i"]
+        else:
+            assert event["partner"] == PartnerKind.Container
+            assert event["j"] is None
+        kinds.append(event["partner"])
+    assert kinds == [PartnerKind.Ball, PartnerKind.Container,
+                     PartnerKind.Container, PartnerKind.Ball]